@@ -0,0 +1,91 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+
+use crate::io;
+
+/// The name of the cache file in the output folder.
+pub const CACHE_FILE: &str = ".cooklatex-cache";
+
+/// Remembers a hash of every generated recipe file between runs, so files
+/// whose content didn't change are not written again. The hash is of the
+/// generated content, so changes to the recipe and to any option that
+/// affects it are both picked up.
+#[derive(Debug)]
+pub struct BuildCache {
+    path: PathBuf,
+    previous: HashMap<String, String>,
+    current: Mutex<BTreeMap<String, String>>,
+}
+
+impl BuildCache {
+    /// Loads the cache of `output_dir`, starting empty if there is none. An
+    /// unreadable cache is ignored, which only means everything is rebuilt.
+    pub fn load(output_dir: &Path) -> Self {
+        let path = output_dir.join(CACHE_FILE);
+        let previous = match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid cache {}: {e}", path.display());
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path,
+            previous,
+            current: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Whether `output_dir` holds the cache of an earlier run, which makes it
+    /// an output folder of ours.
+    pub fn exists(output_dir: &Path) -> bool {
+        output_dir.join(CACHE_FILE).is_file()
+    }
+
+    /// Whether the generated file at `relative_path` already holds `contents`.
+    pub fn is_unchanged(&self, output_dir: &Path, relative_path: &str, contents: &str) -> bool {
+        self.previous.get(relative_path) == Some(&content_hash(contents))
+            && output_dir.join(relative_path).is_file()
+    }
+
+    /// Records the hash of `contents` for the generated file at
+    /// `relative_path`, once the file on disk holds them.
+    pub fn record(&self, relative_path: &str, contents: &str) {
+        self.current
+            .lock()
+            .unwrap()
+            .insert(relative_path.to_string(), content_hash(contents));
+    }
+
+    /// The files generated by the previous run, relative to the output folder.
+    pub fn previous_files(&self) -> impl Iterator<Item = &str> {
+        self.previous.keys().map(String::as_str)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the hashes of the files generated in this run, files that were
+    /// not generated again are dropped from the cache.
+    pub fn save(&self) -> Result<()> {
+        let current = self.current.lock().unwrap();
+        let text = toml::to_string(&*current).context("Failed to serialize cache")?;
+        io::write_file(&self.path, &text)
+    }
+}
+
+/// The hash is not stable between Rust versions, a changed hash only causes
+/// the file to be written again.
+fn content_hash(contents: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
@@ -1,23 +1,699 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{
+    error::ErrorKind, parser::ValueSource, ArgAction, ArgMatches, CommandFactory, FromArgMatches,
+    Parser, Subcommand, ValueEnum,
+};
 use cooklang::convert::System;
+use log::LevelFilter;
+use serde::Deserialize;
+
+use cooklatex::{
+    format::IngredientSort,
+    pdf::Engine,
+    recipe::{EmitFormat, Numbering, OutputFormat},
+};
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Read options from FILE instead of ./cooklatex.toml, flags still take precedence
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
     #[arg(short, long, help = "The folder containing the LaTeX templates")]
-    pub latex_dir: PathBuf,
+    pub latex_dir: Option<PathBuf>,
 
     #[arg(short = 'o', long, help = "The folder to output the LaTeX files to")]
-    pub latex_out_dir: PathBuf,
+    pub latex_out_dir: Option<PathBuf>,
+
+    /// Write a single compilable document instead of filling a template folder
+    #[arg(long, value_name = "OUTPUT.tex", conflicts_with_all = ["latex_dir", "latex_out_dir"])]
+    pub standalone: Option<PathBuf>,
+
+    /// Read a single recipe from stdin and print its LaTeX, without collections or templates
+    #[arg(
+        long,
+        conflicts_with_all = ["standalone", "latex_dir", "latex_out_dir", "collections", "output_format"]
+    )]
+    pub stdin: bool,
 
     pub collections: Vec<PathBuf>,
 
-    /// Convert to a unit system
-    #[arg(short, long, alias = "system", value_name = "SYSTEM")]
-    pub convert: Option<System>,
+    /// Use every subfolder of DIR containing recipes as a collection, unless collections are given
+    #[arg(long, value_name = "DIR")]
+    pub collections_root: Option<PathBuf>,
+
+    /// How many levels of nested folders in a collection become sections
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub max_depth: usize,
+
+    /// Convert to a unit system, further comma separated systems are shown in parentheses
+    #[arg(
+        short,
+        long,
+        alias = "system",
+        value_name = "SYSTEM",
+        value_delimiter = ','
+    )]
+    pub convert: Vec<System>,
+
+    /// Fail recipes with quantities that can't be converted instead of leaving them unconverted
+    #[arg(long)]
+    pub convert_strict: bool,
+
+    #[arg(
+        short = 'u',
+        long,
+        env = "COOKLATEX_UNITS",
+        help = "Path to a custom units file in TOML format"
+    )]
+    pub units_file: Option<PathBuf>,
+
+    /// Multiply every recipe by a factor before converting (1.0 leaves recipes unchanged)
+    #[arg(short, long, value_name = "FACTOR", default_value_t = 1.0)]
+    pub scale: f64,
+
+    /// Scale every recipe to N servings, recipes without servings are left as is
+    #[arg(long, value_name = "N", conflicts_with = "scale")]
+    pub servings: Option<u32>,
+
+    /// Also scale the timers in steps, by default cooking times are kept
+    #[arg(long)]
+    pub scale_timers: bool,
+
+    /// TOML file mapping units to the text shown for them, e.g. `g = "grams"`
+    #[arg(long, value_name = "FILE")]
+    pub unit_labels: Option<PathBuf>,
+
+    /// Render fractional quantities as fractions (e.g. 1 1/2) instead of decimals
+    #[arg(long)]
+    pub fractions: bool,
+
+    /// Round displayed quantities to a number of decimal places
+    #[arg(long, value_name = "PLACES")]
+    pub round: Option<usize>,
+
+    /// The decimal separator of quantity values, e.g. `,` for 236,6 ml
+    #[arg(long, value_name = "CHAR", default_value_t = '.')]
+    pub decimal_separator: char,
+
+    /// Group the digits of large quantity values, e.g. `' '` for 12 000 g
+    #[arg(long, value_name = "CHAR")]
+    pub thousands_separator: Option<char>,
+
+    /// TOML file overriding the labels used for durations, e.g. `hours = "h"`
+    #[arg(long, value_name = "FILE")]
+    pub time_labels: Option<PathBuf>,
+
+    /// Also show ingredient preparation notes, e.g. (finely diced), in the steps
+    #[arg(long)]
+    pub step_notes: bool,
+
+    /// List each ingredient once, summed over all sections of a recipe
+    #[arg(long)]
+    pub merge_ingredients: bool,
+
+    /// The order of the ingredients, as first mentioned in the steps or alphabetical
+    #[arg(long, value_enum, default_value_t = IngredientSort::default())]
+    pub ingredient_sort: IngredientSort,
+
+    /// Set ingredient lists in N columns, recipes can override it with `ingredient_columns: N`
+    #[arg(long, value_name = "N")]
+    pub ingredient_columns: Option<usize>,
+
+    /// Sum quantities of an ingredient in compatible units, e.g. 100 g and 0.1 kg, into one
+    #[arg(long)]
+    pub normalize_units: bool,
+
+    /// Emit `\ingredient{quantity}{name}` instead of `\ingredient{quantity name}`,
+    /// the template has to define `\ingredient` with two arguments
+    #[arg(long)]
+    pub split_ingredient_args: bool,
+
+    /// Wrap the timers in steps in `\timer{duration}{name}` so the template can style them
+    #[arg(long)]
+    pub timer_commands: bool,
+
+    /// Keep the line breaks of steps written over multiple lines instead of joining them
+    #[arg(long)]
+    pub step_line_breaks: bool,
+
+    /// Show the instruction section header even for recipes with a single named section
+    #[arg(long)]
+    pub always_sections: bool,
+
+    /// TOML file mapping `layout` metadata values to environments, e.g. `drink = "recipedrink"`
+    #[arg(long, value_name = "FILE")]
+    pub layouts: Option<PathBuf>,
+
+    /// Title recipes without a title after their file name, e.g. `Banana Bread`, instead of failing them
+    #[arg(long)]
+    pub title_from_filename: bool,
+
+    /// Pass the custom metadata KEY to the template as `\recipemetaextra{key}{value}`, can be repeated
+    #[arg(long, value_name = "KEY", value_delimiter = ',')]
+    pub meta_key: Vec<String>,
+
+    /// Number the recipe titles, restarting in every collection unless `global`
+    #[arg(
+        long,
+        value_name = "SCOPE",
+        num_args = 0..=1,
+        default_missing_value = "collection"
+    )]
+    pub number_recipes: Option<Numbering>,
+
+    /// Only include recipes tagged with TAG, can be repeated
+    #[arg(long, value_name = "TAG")]
+    pub filter_tag: Vec<String>,
+
+    /// Leave out recipes tagged with TAG, can be repeated
+    #[arg(long, value_name = "TAG")]
+    pub exclude_tag: Vec<String>,
+
+    /// Print the JSON Schema of the recipes written with `--emit json` and exit
+    #[arg(long, exclusive = true)]
+    pub print_schema: bool,
+
+    /// Scale recipe images wider than PX pixels down when copying them, PDFs are left as is
+    #[arg(long, value_name = "PX")]
+    pub max_image_width: Option<u32>,
+
+    /// Output formats written for every recipe, e.g. `latex,json`
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "latex")]
+    pub emit: Vec<EmitFormat>,
+
+    /// The markup language the recipes are written in, Typst templates use main.typ
+    #[arg(
+        long,
+        alias = "format",
+        value_enum,
+        default_value_t = OutputFormat::default(),
+        conflicts_with_all = ["standalone", "index", "pdf"]
+    )]
+    pub output_format: OutputFormat,
+
+    /// The token in main.tex that is replaced with the recipes
+    #[arg(long, value_name = "TOKEN", default_value = "%{{recipes}}")]
+    pub placeholder: String,
+
+    /// TOML file with values for other placeholders in main.tex, e.g. `title = "..."` for %{{title}}
+    #[arg(
+        long,
+        alias = "template-vars",
+        value_name = "FILE.toml",
+        conflicts_with = "standalone"
+    )]
+    pub vars: Option<PathBuf>,
+
+    /// Write the summed ingredients of all recipes to FILE, e.g. to \input it in main.tex
+    #[arg(long, value_name = "FILE.tex")]
+    pub shopping_list: Option<PathBuf>,
+
+    /// TOML file with the cookbook's title, subtitle, author and date, e.g. for %{{book_title}}
+    #[arg(long, value_name = "FILE.toml", conflicts_with = "standalone")]
+    pub book_meta: Option<PathBuf>,
+
+    /// Generate an alphabetical ingredient index at the %{{index}} placeholder
+    #[arg(long)]
+    pub index: bool,
 
-    #[arg(short = 'u', long, help = "Path to a custom units file in TOML format")]
+    /// Maximum number of recipes to transpile in parallel (defaults to all cores)
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Compile the generated LaTeX to a PDF using latexmk
+    #[arg(long)]
+    pub pdf: bool,
+
+    /// Open the compiled PDF in the default viewer, requires --pdf
+    #[arg(long)]
+    pub open: bool,
+
+    /// The LaTeX engine latexmk should use when compiling with --pdf, templates using fontspec need xelatex or lualatex
+    #[arg(long, value_enum, default_value_t = Engine::default())]
+    pub engine: Engine,
+
+    /// Exit with an error if any recipe or collection failed to transpile
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Run the full pipeline but only print the files that would be written
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Write to the output folder even if it already contains files
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Remove the files written by earlier runs before copying the template, instead of only updating changed files
+    #[arg(long)]
+    pub clean: bool,
+
+    /// Write every recipe file again, even if it didn't change since the last run
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Show more output, repeat for more detail
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Don't print the Cooklang parser warnings of the recipes, errors are still shown
+    #[arg(long, alias = "quiet-warnings")]
+    pub no_warnings: bool,
+
+    /// Show less output, repeat to also silence errors
+    #[arg(short, long, action = ArgAction::Count)]
+    pub quiet: u8,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print the unit systems accepted by --convert
+    ListSystems,
+}
+
+/// The name `--convert` accepts for `system`.
+pub fn system_name(system: System) -> &'static str {
+    match system {
+        System::Metric => "metric",
+        System::Imperial => "imperial",
+    }
+}
+
+/// The config file read from the current folder unless `--config` is given.
+const CONFIG_FILE: &str = "cooklatex.toml";
+
+/// The options that can be set in `cooklatex.toml`, named like their flags,
+/// e.g. `latex_dir = "latex"`, `convert = ["metric"]` or `fractions = true`.
+/// Every option is optional, flags on the command line take precedence.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub latex_dir: Option<PathBuf>,
+    pub latex_out_dir: Option<PathBuf>,
+    pub collections: Option<Vec<PathBuf>>,
+    pub collections_root: Option<PathBuf>,
+    pub max_depth: Option<usize>,
+    pub convert: Option<Vec<String>>,
+    pub convert_strict: Option<bool>,
     pub units_file: Option<PathBuf>,
+    pub scale: Option<f64>,
+    pub servings: Option<u32>,
+    pub scale_timers: Option<bool>,
+    pub unit_labels: Option<PathBuf>,
+    pub fractions: Option<bool>,
+    pub round: Option<usize>,
+    pub decimal_separator: Option<char>,
+    pub thousands_separator: Option<char>,
+    pub time_labels: Option<PathBuf>,
+    pub step_notes: Option<bool>,
+    pub merge_ingredients: Option<bool>,
+    pub ingredient_sort: Option<String>,
+    pub ingredient_columns: Option<usize>,
+    pub normalize_units: Option<bool>,
+    pub split_ingredient_args: Option<bool>,
+    pub timer_commands: Option<bool>,
+    pub step_line_breaks: Option<bool>,
+    pub always_sections: Option<bool>,
+    pub layouts: Option<PathBuf>,
+    pub title_from_filename: Option<bool>,
+    pub meta_key: Option<Vec<String>>,
+    pub number_recipes: Option<String>,
+    pub filter_tag: Option<Vec<String>>,
+    pub exclude_tag: Option<Vec<String>>,
+    pub max_image_width: Option<u32>,
+    pub emit: Option<Vec<String>>,
+    pub output_format: Option<String>,
+    pub placeholder: Option<String>,
+    pub vars: Option<PathBuf>,
+    pub shopping_list: Option<PathBuf>,
+    pub book_meta: Option<PathBuf>,
+    pub index: Option<bool>,
+    pub jobs: Option<usize>,
+    pub pdf: Option<bool>,
+    pub open: Option<bool>,
+    pub engine: Option<String>,
+    pub strict: Option<bool>,
+    pub dry_run: Option<bool>,
+    pub force: Option<bool>,
+    pub clean: Option<bool>,
+    pub no_cache: Option<bool>,
+    pub verbose: Option<u8>,
+    pub no_warnings: Option<bool>,
+    pub quiet: Option<u8>,
+}
+
+impl Config {
+    /// Loads the config file given with `--config`, or `cooklatex.toml` in
+    /// the current folder if it exists.
+    fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(CONFIG_FILE).is_file() => Path::new(CONFIG_FILE),
+            None => return Ok(Self::default()),
+        };
+
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot find config file: {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+}
+
+/// Parses a config value the way the flag `key` would parse it.
+fn parse_value<T: ValueEnum>(key: &str, value: &str) -> Result<T> {
+    T::from_str(value, true).map_err(|_| anyhow::anyhow!("Invalid {key} in config file: {value}"))
+}
+
+/// Replaces `target` with the configured `value`, unless the flag was given.
+fn merge<T>(target: &mut T, given: bool, value: Option<T>) {
+    if let (false, Some(value)) = (given, value) {
+        *target = value;
+    }
+}
+
+impl Cli {
+    /// Parses the command line, options it doesn't set are taken from the
+    /// config file.
+    pub fn parse_with_config() -> Result<Self> {
+        let matches = Self::command().get_matches();
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+        if cli.command.is_none() {
+            let config = Config::load(cli.config.as_deref())?;
+            cli.apply_config(config, &matches)?;
+            cli.check_required();
+        }
+
+        Ok(cli)
+    }
+
+    fn apply_config(&mut self, config: Config, matches: &ArgMatches) -> Result<()> {
+        // Environment variables count as given, only defaults are replaced
+        let given = |id: &str| {
+            !matches!(
+                matches.value_source(id),
+                None | Some(ValueSource::DefaultValue)
+            )
+        };
+
+        // The template folders would conflict with the other modes
+        if self.standalone.is_none() && !self.stdin {
+            merge(
+                &mut self.latex_dir,
+                given("latex_dir"),
+                config.latex_dir.map(Some),
+            );
+            merge(
+                &mut self.latex_out_dir,
+                given("latex_out_dir"),
+                config.latex_out_dir.map(Some),
+            );
+            merge(&mut self.vars, given("vars"), config.vars.map(Some));
+            merge(
+                &mut self.book_meta,
+                given("book_meta"),
+                config.book_meta.map(Some),
+            );
+        }
+        // A root given on the command line replaces the configured collections
+        if !given("collections") && !given("collections_root") && !self.stdin {
+            merge(&mut self.collections, false, config.collections);
+            merge(
+                &mut self.collections_root,
+                false,
+                config.collections_root.map(Some),
+            );
+        }
+        merge(&mut self.max_depth, given("max_depth"), config.max_depth);
+
+        let convert = config
+            .convert
+            .map(|systems| {
+                systems
+                    .iter()
+                    .map(|system| {
+                        system
+                            .parse()
+                            .map_err(|_| anyhow::anyhow!("Invalid system in config file: {system}"))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?;
+        merge(&mut self.convert, given("convert"), convert);
+        merge(
+            &mut self.convert_strict,
+            given("convert_strict"),
+            config.convert_strict,
+        );
+        merge(
+            &mut self.units_file,
+            given("units_file"),
+            config.units_file.map(Some),
+        );
+
+        // Scaling by a factor and to a number of servings exclude each other
+        if !given("servings") {
+            merge(&mut self.scale, given("scale"), config.scale);
+        }
+        if !given("scale") {
+            merge(&mut self.servings, false, config.servings.map(Some));
+        }
+        merge(
+            &mut self.scale_timers,
+            given("scale_timers"),
+            config.scale_timers,
+        );
+        merge(
+            &mut self.unit_labels,
+            given("unit_labels"),
+            config.unit_labels.map(Some),
+        );
+        merge(&mut self.fractions, given("fractions"), config.fractions);
+        merge(&mut self.round, given("round"), config.round.map(Some));
+        merge(
+            &mut self.decimal_separator,
+            given("decimal_separator"),
+            config.decimal_separator,
+        );
+        merge(
+            &mut self.thousands_separator,
+            given("thousands_separator"),
+            config.thousands_separator.map(Some),
+        );
+        merge(
+            &mut self.time_labels,
+            given("time_labels"),
+            config.time_labels.map(Some),
+        );
+        merge(&mut self.step_notes, given("step_notes"), config.step_notes);
+        merge(
+            &mut self.merge_ingredients,
+            given("merge_ingredients"),
+            config.merge_ingredients,
+        );
+        let ingredient_sort = config
+            .ingredient_sort
+            .map(|sort| parse_value("ingredient_sort", &sort))
+            .transpose()?;
+        merge(
+            &mut self.ingredient_sort,
+            given("ingredient_sort"),
+            ingredient_sort,
+        );
+        merge(
+            &mut self.ingredient_columns,
+            given("ingredient_columns"),
+            config.ingredient_columns.map(Some),
+        );
+        merge(
+            &mut self.normalize_units,
+            given("normalize_units"),
+            config.normalize_units,
+        );
+        merge(
+            &mut self.split_ingredient_args,
+            given("split_ingredient_args"),
+            config.split_ingredient_args,
+        );
+        merge(
+            &mut self.timer_commands,
+            given("timer_commands"),
+            config.timer_commands,
+        );
+        merge(
+            &mut self.step_line_breaks,
+            given("step_line_breaks"),
+            config.step_line_breaks,
+        );
+        merge(
+            &mut self.always_sections,
+            given("always_sections"),
+            config.always_sections,
+        );
+        merge(
+            &mut self.layouts,
+            given("layouts"),
+            config.layouts.map(Some),
+        );
+        merge(
+            &mut self.title_from_filename,
+            given("title_from_filename"),
+            config.title_from_filename,
+        );
+        merge(&mut self.meta_key, given("meta_key"), config.meta_key);
+        let number_recipes = config
+            .number_recipes
+            .map(|scope| parse_value("number_recipes", &scope))
+            .transpose()?;
+        merge(
+            &mut self.number_recipes,
+            given("number_recipes"),
+            number_recipes.map(Some),
+        );
+        merge(&mut self.filter_tag, given("filter_tag"), config.filter_tag);
+        merge(
+            &mut self.exclude_tag,
+            given("exclude_tag"),
+            config.exclude_tag,
+        );
+        merge(
+            &mut self.max_image_width,
+            given("max_image_width"),
+            config.max_image_width.map(Some),
+        );
+        let emit = config
+            .emit
+            .map(|formats| {
+                formats
+                    .iter()
+                    .map(|format| parse_value("emit", format))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?;
+        merge(&mut self.emit, given("emit"), emit);
+        merge(
+            &mut self.placeholder,
+            given("placeholder"),
+            config.placeholder,
+        );
+        merge(
+            &mut self.shopping_list,
+            given("shopping_list"),
+            config.shopping_list.map(Some),
+        );
+        merge(&mut self.index, given("index"), config.index);
+        merge(&mut self.jobs, given("jobs"), config.jobs.map(Some));
+        merge(&mut self.pdf, given("pdf"), config.pdf);
+        merge(&mut self.open, given("open"), config.open);
+        let engine = config
+            .engine
+            .map(|engine| parse_value("engine", &engine))
+            .transpose()?;
+        merge(&mut self.engine, given("engine"), engine);
+
+        // Typst can't be combined with the LaTeX only modes
+        let output_format = config
+            .output_format
+            .map(|format| parse_value("output_format", &format))
+            .transpose()?;
+        merge(
+            &mut self.output_format,
+            given("output_format"),
+            output_format,
+        );
+        if self.output_format == OutputFormat::Typst
+            && (self.standalone.is_some() || self.index || self.pdf)
+        {
+            anyhow::bail!(
+                "output_format = \"typst\" can't be used with --standalone, --index or --pdf"
+            );
+        }
+
+        merge(&mut self.strict, given("strict"), config.strict);
+        merge(&mut self.dry_run, given("dry_run"), config.dry_run);
+        merge(&mut self.force, given("force"), config.force);
+        merge(&mut self.clean, given("clean"), config.clean);
+        merge(&mut self.no_cache, given("no_cache"), config.no_cache);
+        merge(
+            &mut self.no_warnings,
+            given("no_warnings"),
+            config.no_warnings,
+        );
+        // Verbose and quiet exclude each other like their flags
+        if !given("verbose") && !given("quiet") {
+            merge(&mut self.verbose, false, config.verbose);
+            merge(&mut self.quiet, false, config.quiet);
+        }
+
+        Ok(())
+    }
+
+    /// The template folders are required unless another mode is selected,
+    /// checked after the config file is applied.
+    fn check_required(&self) {
+        if self.standalone.is_some() || self.stdin || self.print_schema {
+            return;
+        }
+
+        for (value, flag) in [
+            (&self.latex_dir, "--latex-dir"),
+            (&self.latex_out_dir, "--latex-out-dir"),
+        ] {
+            if value.is_none() {
+                Self::command()
+                    .error(
+                        ErrorKind::MissingRequiredArgument,
+                        format!("{flag} is required, on the command line or in {CONFIG_FILE}"),
+                    )
+                    .exit();
+            }
+        }
+    }
+
+    /// The folder generated files (and copied images) are written to. In
+    /// standalone mode this is the folder of the standalone document.
+    pub fn output_dir(&self) -> PathBuf {
+        match (&self.standalone, &self.latex_out_dir) {
+            (Some(file), _) => file.parent().map(Path::to_path_buf).unwrap_or_default(),
+            (None, Some(dir)) => dir.clone(),
+            (None, None) => PathBuf::new(),
+        }
+    }
+
+    /// The units file given with `--units-file` or `COOKLATEX_UNITS`, falling
+    /// back to `$XDG_CONFIG_HOME/cooklatex/units.toml` if it exists.
+    pub fn units_file(&self) -> Option<PathBuf> {
+        if let Some(units_file) = &self.units_file {
+            return Some(units_file.clone());
+        }
+
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+        Some(config_dir.join("cooklatex").join("units.toml")).filter(|path| path.is_file())
+    }
+
+    pub fn log_level(&self) -> LevelFilter {
+        match (self.verbose, self.quiet) {
+            (0, 0) => LevelFilter::Warn,
+            (0, 1) => LevelFilter::Error,
+            (0, _) => LevelFilter::Off,
+            (1, _) => LevelFilter::Info,
+            (2, _) => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
 }
@@ -0,0 +1,200 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    io,
+    recipe::OutputFormat,
+    render::{render_markdown, RecipeRenderer},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+const ORDER_FILE: &str = "order.toml";
+
+/// The optional `order.toml` of a collection, listing recipe file names (with
+/// or without the `.cook` extension) in the order they should appear.
+#[derive(Deserialize)]
+struct CollectionOrder {
+    recipes: Vec<String>,
+}
+
+/// The subfolders of `root` containing recipes, sorted by name. Folders
+/// holding nothing but nested folders count if those contain recipes, up to
+/// `max_depth` levels below the subfolder.
+pub fn find_collections(root: &Path, max_depth: usize) -> Result<Vec<PathBuf>> {
+    let mut collections = Vec::new();
+
+    for path in io::list_dir(root)? {
+        if path.is_dir() && contains_recipes(&path, max_depth)? {
+            collections.push(path);
+        }
+    }
+
+    collections.sort();
+    Ok(collections)
+}
+
+/// Whether `folder`, or one of its subfolders up to `max_depth` levels
+/// deep, has recipe files.
+fn contains_recipes(folder: &Path, max_depth: usize) -> Result<bool> {
+    if has_recipe_files(folder)? {
+        return Ok(true);
+    }
+    if max_depth == 0 {
+        return Ok(false);
+    }
+
+    for path in io::list_dir(folder)? {
+        if path.is_dir() && contains_recipes(&path, max_depth - 1)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+pub fn has_recipe_files(folder: &Path) -> Result<bool> {
+    Ok(io::list_dir(folder)?
+        .iter()
+        .any(|file| is_recipe_file(file)))
+}
+
+fn is_recipe_file(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| ext == "cook")
+}
+
+/// Keeps the recipe files of a collection, ordered by the collection's order
+/// file. Files not listed there follow alphabetically.
+pub fn order_recipe_files(collection_path: &Path, mut files: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    files.retain(|file| is_recipe_file(file));
+    files.sort();
+
+    let order_file = collection_path.join(ORDER_FILE);
+    if !order_file.is_file() {
+        return Ok(files);
+    }
+
+    let order: CollectionOrder = toml::from_str(&io::read_file(&order_file)?)
+        .with_context(|| format!("Invalid order file: {}", order_file.display()))?;
+
+    files.sort_by_key(|file| {
+        order
+            .recipes
+            .iter()
+            .position(|listed| {
+                file.file_name().is_some_and(|name| name == listed.as_str())
+                    || file.file_stem().is_some_and(|stem| stem == listed.as_str())
+            })
+            .unwrap_or(usize::MAX)
+    });
+
+    Ok(files)
+}
+
+/// Reads the optional `preamble.tex` (`preamble.typ` for Typst) of a
+/// collection, which is spliced into the main file.
+pub fn get_collection_preamble(
+    collection_path: &Path,
+    format: OutputFormat,
+) -> Result<Option<String>> {
+    let path = collection_path.join(format!("preamble.{}", format.extension()));
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    io::read_file(&path).map(Some)
+}
+
+/// Reads the optional intro of a collection, shown right after its chapter
+/// heading. `intro.tex` (`intro.typ` for Typst) is used as is, an `intro.md`
+/// is converted.
+pub fn get_collection_intro<R: RecipeRenderer>(
+    collection_path: &Path,
+    format: OutputFormat,
+) -> Result<Option<String>> {
+    let path = collection_path.join(format!("intro.{}", format.extension()));
+    if path.is_file() {
+        return io::read_file(&path).map(Some);
+    }
+
+    let path = collection_path.join("intro.md");
+    if path.is_file() {
+        return io::read_file(&path).map(|markdown| Some(render_markdown::<R>(&markdown)));
+    }
+
+    Ok(None)
+}
+
+pub fn get_collection_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .context("Invalid collection path")?
+        .to_str()
+        .context("Invalid collection name")
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        recipe::TranspileOptions,
+        testing::{recipe, Cookbook},
+    };
+
+    #[test]
+    fn order_file_sets_the_recipe_order() {
+        let cookbook = Cookbook::new();
+        cookbook
+            .add("Breakfast", "pancakes.cook", &recipe("Mix."))
+            .add("Breakfast", "toast.cook", &recipe("Toast."))
+            .add("Breakfast", "eggs.cook", &recipe("Fry."))
+            .add(
+                "Breakfast",
+                ORDER_FILE,
+                "recipes = [\"toast\", \"pancakes.cook\"]\n",
+            );
+
+        let transpiled = cookbook.transpile("Breakfast", TranspileOptions::default());
+        let paths: Vec<_> = transpiled.recipes.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "Breakfast/toast.tex",
+                "Breakfast/pancakes.tex",
+                "Breakfast/eggs.tex"
+            ]
+        );
+    }
+
+    #[test]
+    fn recipes_are_sorted_by_name_without_an_order_file() {
+        let cookbook = Cookbook::new();
+        cookbook
+            .add("Breakfast", "toast.cook", &recipe("Toast."))
+            .add("Breakfast", "pancakes.cook", &recipe("Mix."));
+
+        let transpiled = cookbook.transpile("Breakfast", TranspileOptions::default());
+        let paths: Vec<_> = transpiled.recipes.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["Breakfast/pancakes.tex", "Breakfast/toast.tex"]);
+    }
+
+    #[test]
+    fn collections_holding_only_nested_folders_are_found() {
+        let cookbook = Cookbook::new();
+        cookbook
+            .add("Desserts/Cakes/Chocolate", "cake.cook", &recipe("Bake."))
+            .add("Breakfast", "pancakes.cook", &recipe("Mix."));
+        std::fs::create_dir_all(cookbook.collection("Empty/Nothing")).unwrap();
+        let root = cookbook.collection("");
+
+        assert_eq!(
+            find_collections(&root, 2).unwrap(),
+            [
+                cookbook.collection("Breakfast"),
+                cookbook.collection("Desserts")
+            ]
+        );
+        assert_eq!(
+            find_collections(&root, 1).unwrap(),
+            [cookbook.collection("Breakfast")]
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use cooklang::{
+    quantity::{Number, Value},
+    Quantity,
+};
+use serde::Deserialize;
+
+use crate::render::RecipeRenderer;
+
+/// The order of the ingredients in the ingredient list.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IngredientSort {
+    /// In the order they are first mentioned in the steps
+    #[default]
+    Mention,
+    /// Alphabetically, ignoring case
+    Alpha,
+}
+
+/// Controls how quantities are rendered in the generated LaTeX.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Render common fractions with `\nicefrac` instead of decimals
+    pub fractions: bool,
+    /// Round quantity values to this many decimal places for display
+    pub round: Option<usize>,
+    /// Labels used when formatting durations
+    pub time_labels: TimeLabels,
+    /// Keep ingredient preparation notes in the step text
+    pub step_notes: bool,
+    /// Sum ingredients over all sections into a single list
+    pub merge_ingredients: bool,
+    /// The order of the ingredients within every section
+    pub ingredient_sort: IngredientSort,
+    /// Set the ingredient list in this many columns with `multicols`
+    pub ingredient_columns: Option<usize>,
+    /// Sum compatible units of an ingredient, e.g. `g` and `kg`, and show the
+    /// total in the most fitting unit
+    pub normalize_units: bool,
+    /// Show the instruction section header even if the recipe has a single section
+    pub always_sections: bool,
+    /// Separates the integer and fractional part of quantity values
+    pub decimal_separator: char,
+    /// Groups the digits of the integer part of quantity values, e.g. `12 000`
+    pub thousands_separator: Option<char>,
+    /// Display text of units, by the unit as written or converted, e.g. `g` → `grams`
+    pub unit_labels: HashMap<String, String>,
+    /// Pass the quantity and name of an ingredient as separate arguments
+    pub split_ingredient_args: bool,
+    /// Wrap timers in steps in `\timer{duration}{name}` instead of plain text
+    pub timer_commands: bool,
+    /// Keep the line breaks of steps spanning multiple lines
+    pub step_line_breaks: bool,
+    /// Custom metadata keys passed to the template as `\recipemetaextra{key}{value}`
+    pub extra_meta_keys: Vec<String>,
+    /// The environment of every `layout` a recipe can select, e.g. `compact`
+    /// → `recipecompact`, recipes without a layout use `recipe`
+    pub layouts: HashMap<String, String>,
+    /// Fail recipes without servings instead of leaving the servings empty
+    pub require_servings: bool,
+    /// Name recipes without a title after their file instead of failing them
+    pub title_from_filename: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            fractions: false,
+            round: None,
+            time_labels: TimeLabels::default(),
+            step_notes: false,
+            merge_ingredients: false,
+            ingredient_sort: IngredientSort::default(),
+            ingredient_columns: None,
+            normalize_units: false,
+            always_sections: false,
+            decimal_separator: '.',
+            thousands_separator: None,
+            unit_labels: HashMap::new(),
+            split_ingredient_args: false,
+            timer_commands: false,
+            step_line_breaks: false,
+            extra_meta_keys: Vec::new(),
+            layouts: default_layouts(),
+            require_servings: true,
+            title_from_filename: false,
+        }
+    }
+}
+
+/// The layouts the example template defines.
+pub fn default_layouts() -> HashMap<String, String> {
+    HashMap::from([("compact".to_string(), "recipecompact".to_string())])
+}
+
+impl FormatOptions {
+    /// The separator between multiple quantities, a comma would be ambiguous
+    /// with a decimal comma.
+    pub fn list_separator(&self) -> &'static str {
+        if self.decimal_separator == ',' {
+            "; "
+        } else {
+            ", "
+        }
+    }
+}
+
+/// The singular and plural labels of the units used for durations.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TimeLabels {
+    pub second: String,
+    pub seconds: String,
+    pub minute: String,
+    pub minutes: String,
+    pub hour: String,
+    pub hours: String,
+    pub day: String,
+    pub days: String,
+}
+
+impl Default for TimeLabels {
+    fn default() -> Self {
+        Self {
+            second: "sec".to_string(),
+            seconds: "secs".to_string(),
+            minute: "min".to_string(),
+            minutes: "mins".to_string(),
+            hour: "hr".to_string(),
+            hours: "hrs".to_string(),
+            day: "day".to_string(),
+            days: "days".to_string(),
+        }
+    }
+}
+
+const FRACTION_DENOMINATORS: [u32; 4] = [2, 3, 4, 8];
+const FRACTION_TOLERANCE: f64 = 0.01;
+
+/// Formats a quantity in the markup of `R`, the result is already escaped.
+pub fn format_quantity<R: RecipeRenderer>(qty: &Quantity, options: &FormatOptions) -> String {
+    let value = format_value::<R>(qty.value(), options);
+
+    match qty.unit() {
+        Some(unit) => {
+            let unit = options.unit_labels.get(unit).map_or(unit, String::as_str);
+            format!("{value} {}", R::escape(unit))
+        }
+        None => value,
+    }
+}
+
+fn format_value<R: RecipeRenderer>(value: &Value, options: &FormatOptions) -> String {
+    match value {
+        Value::Number(number) => format_number_value::<R>(number, options),
+        // An en dash between the bounds, e.g. `2--3 cloves`
+        Value::Range { start, end } => format!(
+            "{}--{}",
+            format_number_value::<R>(start, options),
+            format_number_value::<R>(end, options)
+        ),
+        Value::Text(text) => R::escape(text),
+    }
+}
+
+fn format_number_value<R: RecipeRenderer>(number: &Number, options: &FormatOptions) -> String {
+    let value = match options.round {
+        Some(places) => round_number(number.value(), places),
+        None => number.value(),
+    };
+
+    let formatted = if options.fractions {
+        format_fraction::<R>(value)
+    } else if let Some(places) = options.round {
+        format_rounded(value, places)
+    } else {
+        R::escape(&number.to_string())
+    };
+
+    let formatted = if options.decimal_separator == '.' {
+        formatted
+    } else {
+        formatted.replace('.', &R::escape(&options.decimal_separator.to_string()))
+    };
+
+    // Grouped after replacing the decimal separator, so a `.` used for
+    // grouping is not replaced again
+    match options.thousands_separator {
+        // A thin space, which also keeps the number on one line
+        Some(' ') => group_thousands(&formatted, R::THIN_SPACE),
+        Some(separator) => group_thousands(&formatted, &R::escape(&separator.to_string())),
+        None => formatted,
+    }
+}
+
+/// Inserts `separator` between every three digits of the leading integer
+/// part of `number`, e.g. `12000.5` becomes `12 000.5`.
+fn group_thousands(number: &str, separator: &str) -> String {
+    let digits = number.chars().take_while(char::is_ascii_digit).count();
+    let (integer, rest) = number.split_at(digits);
+
+    let mut grouped = String::with_capacity(number.len() + digits / 3 * separator.len());
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (digits - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped.push_str(rest);
+    grouped
+}
+
+fn round_number(number: f64, places: usize) -> f64 {
+    let factor = 10f64.powi(places as i32);
+    (number * factor).round() / factor
+}
+
+/// Formats `number` with at most `places` decimals, without trailing zeros.
+fn format_rounded(number: f64, places: usize) -> String {
+    let formatted = format!("{number:.places$}");
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        formatted
+    }
+}
+
+pub fn format_number(number: f64) -> String {
+    // f64's Display already omits a trailing `.0` for whole numbers
+    format!("{number}")
+}
+
+/// Renders `value` as a (mixed) fraction, e.g. `\nicefrac` in LaTeX, falling
+/// back to the decimal representation when no common fraction is close enough.
+fn format_fraction<R: RecipeRenderer>(value: f64) -> String {
+    let whole = value.trunc();
+    let fraction = value - whole;
+
+    if fraction < FRACTION_TOLERANCE {
+        return format_number(whole);
+    }
+    if 1.0 - fraction < FRACTION_TOLERANCE {
+        return format_number(whole + 1.0);
+    }
+
+    for denominator in FRACTION_DENOMINATORS {
+        let numerator = (fraction * denominator as f64).round() as u32;
+        if numerator == 0 || numerator >= denominator {
+            continue;
+        }
+
+        if (fraction - numerator as f64 / denominator as f64).abs() < FRACTION_TOLERANCE {
+            let fraction = R::fraction(numerator, denominator);
+            return if whole > 0.0 {
+                format!("{}{fraction}", format_number(whole))
+            } else {
+                fraction
+            };
+        }
+    }
+
+    format_number(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::quantity, LatexBuilder};
+
+    fn format(value: f64, options: &FormatOptions) -> String {
+        format_quantity::<LatexBuilder>(&quantity(value, None), options)
+    }
+
+    #[test]
+    fn fractions_render_common_fractions() {
+        let options = FormatOptions {
+            fractions: true,
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(format(0.5, &options), r"\nicefrac{1}{2}");
+        assert_eq!(format(0.25, &options), r"\nicefrac{1}{4}");
+        assert_eq!(format(1.0 / 3.0, &options), r"\nicefrac{1}{3}");
+        assert_eq!(format(2.75, &options), r"2\nicefrac{3}{4}");
+        assert_eq!(format(1.5, &options), r"1\nicefrac{1}{2}");
+    }
+
+    #[test]
+    fn fractions_fall_back_to_decimals() {
+        let options = FormatOptions {
+            fractions: true,
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(format(0.3, &options), "0.3");
+        assert_eq!(format(2.0, &options), "2");
+    }
+
+    #[test]
+    fn decimals_are_kept_without_fractions() {
+        assert_eq!(format(0.5, &FormatOptions::default()), "0.5");
+    }
+
+    #[test]
+    fn round_limits_the_decimal_places() {
+        let round = |places| FormatOptions {
+            round: Some(places),
+            ..FormatOptions::default()
+        };
+        let qty = quantity(236.588, Some("ml"));
+
+        assert_eq!(format_quantity::<LatexBuilder>(&qty, &round(0)), "237 ml");
+        assert_eq!(format_quantity::<LatexBuilder>(&qty, &round(1)), "236.6 ml");
+        assert_eq!(format(2.5, &round(2)), "2.5");
+    }
+
+    #[test]
+    fn decimal_separator_replaces_the_point() {
+        let qty = quantity(236.6, Some("ml"));
+        let comma = FormatOptions {
+            decimal_separator: ',',
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(format_quantity::<LatexBuilder>(&qty, &comma), "236,6 ml");
+        assert_eq!(
+            format_quantity::<LatexBuilder>(&qty, &FormatOptions::default()),
+            "236.6 ml"
+        );
+        assert_eq!(comma.list_separator(), "; ");
+        assert_eq!(FormatOptions::default().list_separator(), ", ");
+    }
+
+    #[test]
+    fn thousands_are_grouped() {
+        let thin = FormatOptions {
+            thousands_separator: Some(' '),
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(format(1000.0, &thin), r"1\,000");
+        assert_eq!(format(12000.0, &thin), r"12\,000");
+        assert_eq!(format(12000.5, &thin), r"12\,000.5");
+        assert_eq!(format(999.0, &thin), "999");
+    }
+
+    #[test]
+    fn thousands_separator_works_with_a_decimal_comma() {
+        let options = FormatOptions {
+            decimal_separator: ',',
+            thousands_separator: Some('.'),
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(format(12000.5, &options), "12.000,5");
+    }
+
+    #[test]
+    fn unit_labels_replace_the_unit() {
+        let options = FormatOptions {
+            unit_labels: HashMap::from([("g".to_string(), "grams".to_string())]),
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            format_quantity::<LatexBuilder>(&quantity(200.0, Some("g")), &options),
+            "200 grams"
+        );
+        assert_eq!(
+            format_quantity::<LatexBuilder>(&quantity(1.0, Some("fl oz")), &options),
+            "1 fl oz"
+        );
+    }
+
+    #[test]
+    fn ranges_are_joined_with_an_en_dash() {
+        let range = Quantity::new(
+            Value::Range {
+                start: Number::from(2.0),
+                end: Number::from(3.0),
+            },
+            Some("cloves".to_string()),
+        );
+        assert_eq!(
+            format_quantity::<LatexBuilder>(&range, &FormatOptions::default()),
+            "2--3 cloves"
+        );
+    }
+}
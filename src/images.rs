@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+
+use crate::io;
+use anyhow::{Context, Result};
+use cooklang::Metadata;
+
+pub fn get_recipe_image(meta: &Metadata) -> Option<&str> {
+    meta.get("image")
+        .or_else(|| meta.get("picture"))
+        .and_then(|image| image.as_str())
+}
+
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "pdf"];
+
+/// Copies the image of a recipe next to its generated file. Images wider than
+/// `max_width` pixels are scaled down, PDFs are always copied as is.
+pub fn copy_recipe_image(
+    out_dir: &Path,
+    collection_name: &str,
+    recipe_file: &Path,
+    image: &str,
+    max_width: Option<u32>,
+) -> Result<String> {
+    let source = recipe_file
+        .parent()
+        .context("Invalid recipe path")?
+        .join(image);
+
+    if !source.is_file() {
+        anyhow::bail!("Image not found: {}", source.display());
+    }
+
+    let extension = source
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .filter(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+        .with_context(|| format!("Unsupported image format: {}", source.display()))?;
+
+    let file_stem = recipe_file
+        .file_stem()
+        .context("Invalid recipe file name")?
+        .to_str()
+        .context("Could not convert to str")?;
+
+    let relative_path = PathBuf::from(collection_name).join(format!("{file_stem}.{extension}"));
+
+    io::create_dir_all(&out_dir.join(collection_name))?;
+    match max_width.filter(|_| extension != "pdf") {
+        Some(max_width) => {
+            io::copy_image_resized(&source, &out_dir.join(&relative_path), max_width)?
+        }
+        None => io::copy_file(&source, &out_dir.join(&relative_path))?,
+    }
+
+    relative_path
+        .to_str()
+        .context("Failed to compute relative path")
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{recipe, Cookbook};
+
+    #[test]
+    fn oversized_images_are_scaled_down() {
+        let cookbook = Cookbook::new();
+        cookbook.add("Breakfast", "pancakes.cook", &recipe("Mix."));
+        let recipe_file = cookbook.collection("Breakfast").join("pancakes.cook");
+        image::RgbImage::new(800, 400)
+            .save(cookbook.collection("Breakfast").join("photo.png"))
+            .unwrap();
+
+        let output_dir = cookbook.output_dir();
+        let path = copy_recipe_image(
+            &output_dir,
+            "Breakfast",
+            &recipe_file,
+            "photo.png",
+            Some(200),
+        )
+        .unwrap();
+        assert_eq!(path, "Breakfast/pancakes.png");
+        assert_eq!(
+            image::image_dimensions(output_dir.join(&path)).unwrap(),
+            (200, 100)
+        );
+    }
+
+    #[test]
+    fn narrow_images_are_copied_unchanged() {
+        let cookbook = Cookbook::new();
+        cookbook.add("Breakfast", "pancakes.cook", &recipe("Mix."));
+        let recipe_file = cookbook.collection("Breakfast").join("pancakes.cook");
+        image::RgbImage::new(100, 50)
+            .save(cookbook.collection("Breakfast").join("photo.png"))
+            .unwrap();
+
+        let output_dir = cookbook.output_dir();
+        let path = copy_recipe_image(
+            &output_dir,
+            "Breakfast",
+            &recipe_file,
+            "photo.png",
+            Some(200),
+        )
+        .unwrap();
+        assert_eq!(
+            image::image_dimensions(output_dir.join(path)).unwrap(),
+            (100, 50)
+        );
+    }
+}
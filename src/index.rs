@@ -0,0 +1,108 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    latex::{sanitize_latex, Arg, LatexBuilder},
+    recipe::TranspiledRecipe,
+};
+
+pub const INDEX_PLACEHOLDER: &str = "%{{index}}";
+
+/// Builds an alphabetical index mapping every ingredient to the recipes using
+/// it. Ingredients are deduplicated case-insensitively, keeping the casing of
+/// the first occurrence. Recipes are told apart by their label, recipes with
+/// the same title in different collections are shown with their collection.
+/// Every recipe links to its label and shows its page, see [`recipe_reference`].
+pub fn build_ingredient_index(recipes: &[TranspiledRecipe]) -> LatexBuilder {
+    let mut index: BTreeMap<String, (&str, Vec<&TranspiledRecipe>)> = BTreeMap::new();
+    let mut title_counts: HashMap<&str, usize> = HashMap::new();
+
+    for recipe in recipes {
+        *title_counts.entry(&recipe.title).or_default() += 1;
+
+        for ingredient in &recipe.ingredients {
+            let key = ingredient.trim().to_lowercase();
+            let (_, used_in) = index.entry(key).or_insert((ingredient.trim(), Vec::new()));
+
+            if !used_in.iter().any(|other| other.label == recipe.label) {
+                used_in.push(recipe);
+            }
+        }
+    }
+
+    let mut entries = LatexBuilder::new();
+    for (name, used_in) in index.values() {
+        let titles = used_in
+            .iter()
+            .map(|recipe| {
+                let title = if title_counts[recipe.title.as_str()] > 1 {
+                    format!("{} ({})", recipe.title, recipe.collection)
+                } else {
+                    recipe.title.clone()
+                };
+                recipe_reference(&sanitize_latex(&title), &recipe.label)
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        entries.add_command(
+            "ingredientindexentry",
+            &[Arg::required(&sanitize_latex(name)), Arg::required(&titles)],
+        );
+    }
+
+    let mut latex = LatexBuilder::new();
+    latex.add_env("ingredientindex", &entries);
+    latex
+}
+
+/// Links the already escaped `title` to the recipe `label` and adds its page,
+/// e.g. `\hyperref[recipe:Desserts:pie]{Pie}, p.~\pageref{recipe:Desserts:pie}`.
+fn recipe_reference(title: &str, label: &str) -> String {
+    format!("\\hyperref[{label}]{{{title}}}, p.~\\pageref{{{label}}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::transpiled;
+
+    #[test]
+    fn index_links_every_recipe_to_its_page() {
+        let recipes = [
+            transpiled("Breakfast", "pancakes", "Pancakes", &["flour", "eggs"]),
+            transpiled("Breakfast", "waffles", "Waffles", &["Flour"]),
+        ];
+
+        let latex = build_ingredient_index(&recipes).build();
+        assert!(
+            latex.contains(
+                r"\ingredientindexentry{eggs}{\hyperref[recipe:Breakfast:pancakes]{Pancakes}, p.~\pageref{recipe:Breakfast:pancakes}}"
+            ),
+            "{latex}"
+        );
+        assert!(
+            latex.contains(
+                r"\ingredientindexentry{flour}{\hyperref[recipe:Breakfast:pancakes]{Pancakes}, p.~\pageref{recipe:Breakfast:pancakes}; \hyperref[recipe:Breakfast:waffles]{Waffles}, p.~\pageref{recipe:Breakfast:waffles}}"
+            ),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn recipes_with_the_same_stem_are_told_apart() {
+        let recipes = [
+            transpiled("Breakfast", "pancakes", "Pancakes", &["flour"]),
+            transpiled("Desserts", "pancakes", "Pancakes", &["flour"]),
+        ];
+
+        let latex = build_ingredient_index(&recipes).build();
+        assert!(
+            latex.contains(r"\hyperref[recipe:Breakfast:pancakes]{Pancakes (Breakfast)}"),
+            "{latex}"
+        );
+        assert!(
+            latex.contains(r"\hyperref[recipe:Desserts:pancakes]{Pancakes (Desserts)}"),
+            "{latex}"
+        );
+    }
+}
@@ -2,18 +2,40 @@ use anyhow::{Context, Result};
 use std::{
     fs,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// In a dry run nothing is written to disk, the paths that would have been
+/// written are printed instead.
+pub fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
 pub fn read_file(path: &Path) -> Result<String> {
     fs::read_to_string(path).with_context(|| format!("Failed to read file: {:?}", path.display()))
 }
 
 pub fn write_file(path: &Path, contents: &str) -> Result<()> {
+    if is_dry_run() {
+        println!("Would write {}", path.display());
+        return Ok(());
+    }
+
     fs::write(path, contents)
         .with_context(|| format!("Failed to write to file: {}", path.display()))
 }
 
 pub fn create_dir_all(path: &Path) -> Result<()> {
+    if is_dry_run() {
+        return Ok(());
+    }
+
     fs::create_dir_all(path)
         .with_context(|| format!("Failed to create directory: {}", path.display()))
 }
@@ -26,20 +48,101 @@ pub fn list_dir(path: &Path) -> Result<Vec<PathBuf>> {
         .collect())
 }
 
+/// Fails when `path` exists and contains files, listing them in the error.
+pub fn ensure_empty_dir(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let existing = list_dir(path)?;
+    if existing.is_empty() {
+        return Ok(());
+    }
+
+    let files = existing
+        .iter()
+        .map(|file| format!("  {}", file.display()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    anyhow::bail!(
+        "Output directory {} is not empty, use --force to overwrite:\n{files}",
+        path.display()
+    )
+}
+
+/// Copies the files of `source` into `target`. Files that are already
+/// identical are left alone, and files only in `target` (e.g. recipes
+/// generated by an earlier run) are kept.
 pub fn clone_folder_to_target(source: &Path, target: &Path) -> Result<()> {
     create_dir_all(target)?;
 
     for file in list_dir(source)? {
         let target_path = target.join(file.file_name().context("Invalid source file name")?);
 
-        fs::copy(&file, &target_path).with_context(|| {
-            format!(
-                "Failed to copy {} to {}",
-                file.display(),
-                target_path.display()
-            )
-        })?;
+        if is_same_file_content(&file, &target_path) {
+            continue;
+        }
+        copy_file(&file, &target_path)?;
+    }
+
+    Ok(())
+}
+
+fn is_same_file_content(a: &Path, b: &Path) -> bool {
+    let (Ok(a_meta), Ok(b_meta)) = (fs::metadata(a), fs::metadata(b)) else {
+        return false;
+    };
+
+    a_meta.len() == b_meta.len() && matches!((fs::read(a), fs::read(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Removes the file at `path`, if it exists.
+pub fn remove_file(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    if is_dry_run() {
+        println!("Would remove {}", path.display());
+        return Ok(());
+    }
+
+    fs::remove_file(path).with_context(|| format!("Failed to remove file: {}", path.display()))
+}
+
+pub fn copy_file(source: &Path, target: &Path) -> Result<()> {
+    if is_dry_run() {
+        println!("Would copy {} to {}", source.display(), target.display());
+        return Ok(());
     }
 
+    fs::copy(source, target).with_context(|| {
+        format!(
+            "Failed to copy {} to {}",
+            source.display(),
+            target.display()
+        )
+    })?;
+
     Ok(())
 }
+
+/// Copies the image at `source`, scaled down to at most `max_width` pixels
+/// wide while keeping its aspect ratio. Narrower images are copied as is.
+pub fn copy_image_resized(source: &Path, target: &Path, max_width: u32) -> Result<()> {
+    if is_dry_run() {
+        println!("Would copy {} to {}", source.display(), target.display());
+        return Ok(());
+    }
+
+    let image = image::open(source)
+        .with_context(|| format!("Failed to read image {}", source.display()))?;
+    if image.width() <= max_width {
+        return copy_file(source, target);
+    }
+
+    image
+        .resize(max_width, u32::MAX, image::imageops::FilterType::Lanczos3)
+        .save(target)
+        .with_context(|| format!("Failed to write image {}", target.display()))
+}
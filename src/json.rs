@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use cooklang::{Content, Converter, Item, Metadata, Quantity, Recipe, Step};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    format::FormatOptions,
+    recipe::{get_ingredients_by_section, group_cookware},
+};
+
+/// The JSON written for every recipe when `--emit json` is given.
+///
+/// The top-level shape is stable:
+///
+/// ```json
+/// {
+///   "title": "Pancakes",
+///   "description": "Fluffy pancakes",
+///   "metadata": { "servings": 4, "...": "..." },
+///   "ingredients": [
+///     {
+///       "section": null,
+///       "ingredients": [{ "name": "flour", "quantities": [{ "value": "200", "unit": "g" }] }]
+///     }
+///   ],
+///   "cookware": [{ "name": "pan", "quantities": [] }],
+///   "steps": ["Mix the flour and the milk."]
+/// }
+/// ```
+#[derive(Serialize, JsonSchema)]
+pub struct RecipeJson<'a> {
+    pub title: Option<&'a str>,
+    pub description: Option<&'a str>,
+    #[schemars(with = "serde_json::Value")]
+    pub metadata: &'a Metadata,
+    pub ingredients: Vec<IngredientSectionJson>,
+    pub cookware: Vec<ItemJson>,
+    pub steps: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct IngredientSectionJson {
+    pub section: Option<String>,
+    pub ingredients: Vec<ItemJson>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ItemJson {
+    pub name: String,
+    pub quantities: Vec<QuantityJson>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct QuantityJson {
+    pub value: String,
+    pub unit: Option<String>,
+}
+
+impl From<&Quantity> for QuantityJson {
+    fn from(qty: &Quantity) -> Self {
+        Self {
+            value: qty.value().to_string(),
+            unit: qty.unit().map(String::from),
+        }
+    }
+}
+
+pub fn create_recipe_json(
+    recipe: &Recipe,
+    converter: &Converter,
+    options: &FormatOptions,
+) -> Result<String> {
+    let ingredients = get_ingredients_by_section(recipe, converter, options)
+        .into_iter()
+        .map(|(section, ingredients)| IngredientSectionJson {
+            section,
+            ingredients: ingredients
+                .iter()
+                .map(|grouped| ItemJson {
+                    name: grouped.ingredient.name.clone(),
+                    quantities: grouped.quantity.iter().map(QuantityJson::from).collect(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    let cookware = group_cookware(recipe, converter)
+        .into_iter()
+        .map(|(cookware, quantity)| ItemJson {
+            name: cookware.name.clone(),
+            quantities: quantity.iter().map(QuantityJson::from).collect(),
+        })
+        .collect();
+
+    let steps = recipe
+        .sections
+        .iter()
+        .flat_map(|section| &section.content)
+        .map(|content| match content {
+            Content::Step(step) => step_plain_text(recipe, step),
+            Content::Text(text) => text.clone(),
+        })
+        .collect();
+
+    let json = RecipeJson {
+        title: recipe.metadata.title(),
+        description: recipe.metadata.description(),
+        metadata: &recipe.metadata,
+        ingredients,
+        cookware,
+        steps,
+    };
+
+    serde_json::to_string_pretty(&json).context("Failed to serialize recipe to JSON")
+}
+
+/// The JSON Schema of [`RecipeJson`], derived from the same struct so the two
+/// can't drift apart.
+pub fn recipe_json_schema() -> Result<String> {
+    let schema = schemars::schema_for!(RecipeJson<'static>);
+    serde_json::to_string_pretty(&schema).context("Failed to serialize JSON schema")
+}
+
+fn step_plain_text(recipe: &Recipe, step: &Step) -> String {
+    step.items
+        .iter()
+        .map(|item| match item {
+            Item::Text { value } => value.clone(),
+            Item::Ingredient { index } => recipe.ingredients[*index].display_name().to_string(),
+            Item::Cookware { index } => recipe.cookware[*index].name.clone(),
+            Item::Timer { index } => {
+                let timer = &recipe.timers[*index];
+                match (&timer.quantity, &timer.name) {
+                    (Some(qty), Some(name)) => format!("{qty} ({name})"),
+                    (Some(qty), None) => qty.to_string(),
+                    (None, Some(name)) => name.clone(),
+                    (None, None) => String::new(),
+                }
+            }
+            Item::InlineQuantity { index } => recipe.inline_quantities[*index].to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_lists_the_top_level_properties() {
+        let schema: serde_json::Value =
+            serde_json::from_str(&recipe_json_schema().unwrap()).unwrap();
+        let properties = schema["properties"]
+            .as_object()
+            .expect("the schema has no properties");
+
+        let mut names: Vec<_> = properties.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            [
+                "cookware",
+                "description",
+                "ingredients",
+                "metadata",
+                "steps",
+                "title"
+            ]
+        );
+    }
+}
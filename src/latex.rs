@@ -1,10 +1,27 @@
+use anyhow::{bail, Result};
+
+use crate::render::RecipeRenderer;
+
+const INDENT: &str = "  ";
+
 #[derive(Default)]
 pub struct LatexBuilder {
-    content: Vec<String>,
+    /// Every line together with its environment nesting depth
+    content: Vec<(usize, String)>,
+    /// Every `\begin` and `\end` in the content, in order, with the index of
+    /// its entry in `content` and its line within that entry
+    environments: Vec<(usize, usize, Environment)>,
+}
+
+#[derive(Clone)]
+enum Environment {
+    Begin(String),
+    End(String),
 }
 
+#[derive(Clone)]
 pub struct Arg {
-    value: String,
+    pub(crate) value: String,
     optional: bool,
 }
 
@@ -42,7 +59,48 @@ impl LatexBuilder {
             })
             .collect();
 
-        self.content.push(format!("\\{command}{formatted_args}"));
+        if let (Some(name), "begin" | "end") = (args.first(), command) {
+            self.add_environment(0, command, &name.value);
+        }
+        self.content
+            .push((0, format!("\\{command}{formatted_args}")));
+        self
+    }
+
+    /// Records a `\begin` or `\end` of the entry about to be added, at `line`
+    /// within it.
+    fn add_environment(&mut self, line: usize, command: &str, name: &str) {
+        let environment = if command == "begin" {
+            Environment::Begin(name.to_string())
+        } else {
+            Environment::End(name.to_string())
+        };
+        self.environments
+            .push((self.content.len(), line, environment));
+    }
+
+    /// Adds `raw` verbatim, without sanitizing or wrapping it in a command.
+    /// The caller is responsible for it being valid LaTeX.
+    pub fn add_raw(&mut self, raw: &str) -> &mut Self {
+        for (i, line) in raw.lines().enumerate() {
+            for command in ["begin", "end"] {
+                for (start, _) in line.match_indices(&format!("\\{command}{{")) {
+                    let rest = &line[start + command.len() + 2..];
+                    if let Some(end) = rest.find('}') {
+                        self.add_environment(i, command, &rest[..end]);
+                    }
+                }
+            }
+        }
+        self.content.push((0, raw.to_string()));
+        self
+    }
+
+    /// Adds `text` as a LaTeX comment, one `%` line per line of text.
+    pub fn add_comment(&mut self, text: &str) -> &mut Self {
+        for line in text.lines() {
+            self.content.push((0, format!("% {line}")));
+        }
         self
     }
 
@@ -51,26 +109,455 @@ impl LatexBuilder {
     }
 
     pub fn add_env(&mut self, env: &str, content: &LatexBuilder) -> &mut Self {
-        self.add_simple_command("begin", env);
-        self.add_builder(content);
+        self.add_env_with_args(env, &[], content)
+    }
+
+    pub fn add_env_with_args(
+        &mut self,
+        env: &str,
+        args: &[Arg],
+        content: &LatexBuilder,
+    ) -> &mut Self {
+        let mut begin_args = vec![Arg::required(env)];
+        begin_args.extend(args.iter().cloned());
+
+        self.add_command("begin", &begin_args);
+        self.add_nested_builder(content, 1);
         self.add_simple_command("end", env)
     }
 
     pub fn add_builder(&mut self, other: &LatexBuilder) -> &mut Self {
-        self.content.extend(other.content.iter().cloned());
+        self.add_nested_builder(other, 0)
+    }
+
+    fn add_nested_builder(&mut self, other: &LatexBuilder, depth: usize) -> &mut Self {
+        let offset = self.content.len();
+        self.environments.extend(
+            other
+                .environments
+                .iter()
+                .map(|(index, line, environment)| (index + offset, *line, environment.clone())),
+        );
+        self.content.extend(
+            other
+                .content
+                .iter()
+                .map(|(line_depth, line)| (line_depth + depth, line.clone())),
+        );
         self
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    /// Joins the lines, indenting the content of environments. TeX ignores
+    /// leading whitespace so the indentation does not change the output.
     pub fn build(&self) -> String {
-        self.content.join("\n")
+        self.content
+            .iter()
+            .map(|(depth, line)| format!("{}{line}", INDENT.repeat(*depth)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Checks the built LaTeX for unbalanced braces and environments, see
+    /// [`validate_latex`].
+    pub fn validate(&self) -> Vec<String> {
+        validate_latex(&self.build())
+    }
+
+    /// Like [`build`](Self::build), but fails if the environments don't
+    /// match, e.g. for a `\begin` added with [`add_raw`](Self::add_raw) that
+    /// is never closed. Braces are not checked, see [`validate`](Self::validate).
+    pub fn build_checked(&self) -> Result<String> {
+        let mut problems = Vec::new();
+        let mut open: Vec<(&str, usize)> = Vec::new();
+
+        for (index, line, environment) in &self.environments {
+            let line_number = self.line_number(*index) + line;
+            match environment {
+                Environment::Begin(name) => open.push((name.as_str(), line_number)),
+                Environment::End(name) => match open.pop() {
+                    Some((begin, _)) if begin == name.as_str() => {}
+                    Some((begin, begin_line)) => problems.push(format!(
+                        "\\end{{{name}}} on line {line_number} closes \\begin{{{begin}}} from line {begin_line}"
+                    )),
+                    None => problems.push(format!(
+                        "\\end{{{name}}} on line {line_number} has no \\begin"
+                    )),
+                },
+            }
+        }
+        for (name, line_number) in open {
+            problems.push(format!(
+                "\\begin{{{name}}} on line {line_number} is never closed"
+            ));
+        }
+
+        if !problems.is_empty() {
+            bail!("Malformed LaTeX: {}", problems.join(", "));
+        }
+        Ok(self.build())
+    }
+
+    /// The line the entry at `index` of the content starts on in the built
+    /// LaTeX.
+    fn line_number(&self, index: usize) -> usize {
+        1 + self.content[..index]
+            .iter()
+            .map(|(_, line)| line.split('\n').count())
+            .sum::<usize>()
+    }
+}
+
+/// Returns a warning for every unbalanced brace and every `\begin` without a
+/// matching `\end`, which would otherwise only show up as a confusing
+/// latexmk error. Escaped braces and comments are ignored.
+pub fn validate_latex(latex: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut open_braces: Vec<usize> = Vec::new();
+    let mut environments: Vec<(String, usize)> = Vec::new();
+
+    for (i, line) in latex.lines().enumerate() {
+        let line_number = i + 1;
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    let mut command = String::new();
+                    while let Some(letter) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+                        command.push(letter);
+                    }
+                    if command.is_empty() {
+                        // An escaped character, e.g. `\{` or `\%`
+                        chars.next();
+                        continue;
+                    }
+                    if (command != "begin" && command != "end") || chars.next_if_eq(&'{').is_none()
+                    {
+                        continue;
+                    }
+
+                    let name: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                    if command == "begin" {
+                        environments.push((name, line_number));
+                    } else {
+                        match environments.pop() {
+                            Some((open, _)) if open == name => {}
+                            Some((open, begin_line)) => warnings.push(format!(
+                                "\\end{{{name}}} on line {line_number} closes \\begin{{{open}}} from line {begin_line}"
+                            )),
+                            None => warnings.push(format!(
+                                "\\end{{{name}}} on line {line_number} has no \\begin"
+                            )),
+                        }
+                    }
+                }
+                '%' => break,
+                '{' => open_braces.push(line_number),
+                '}' => {
+                    if open_braces.pop().is_none() {
+                        warnings.push(format!("Unmatched }} on line {line_number}"));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for line_number in open_braces {
+        warnings.push(format!("Unclosed {{ on line {line_number}"));
+    }
+    for (name, line_number) in environments {
+        warnings.push(format!(
+            "\\begin{{{name}}} on line {line_number} is never closed"
+        ));
+    }
+
+    warnings
+}
+
+impl RecipeRenderer for LatexBuilder {
+    const THIN_SPACE: &'static str = "\\,";
+
+    const LINE_BREAK: &'static str = "\\newline";
+
+    // `\BooleanTrue` of xparse, templates test it with `\IfBooleanT`
+    const OPTIONAL_FLAG: Option<&'static str> = Some("\\BooleanTrue");
+
+    fn escape(text: &str) -> String {
+        sanitize_latex(text)
+    }
+
+    fn fraction(numerator: u32, denominator: u32) -> String {
+        format!("\\nicefrac{{{numerator}}}{{{denominator}}}")
+    }
+
+    fn escape_url(url: &str) -> String {
+        sanitize_url(url)
+    }
+
+    fn link(label: &str, text: &str) -> String {
+        format!("\\hyperref[{label}]{{{text}}}")
+    }
+
+    fn add_command(&mut self, command: &str, args: &[Arg]) -> &mut Self {
+        LatexBuilder::add_command(self, command, args)
+    }
+
+    fn add_env(&mut self, env: &str, content: &Self) -> &mut Self {
+        LatexBuilder::add_env(self, env, content)
+    }
+
+    fn add_columns(&mut self, columns: usize, content: &Self) -> &mut Self {
+        LatexBuilder::add_env_with_args(
+            self,
+            "multicols",
+            &[Arg::required(&columns.to_string())],
+            content,
+        )
+    }
+
+    fn add_label(&mut self, label: &str) -> &mut Self {
+        LatexBuilder::add_simple_command(self, "label", label)
+    }
+
+    fn add_raw(&mut self, raw: &str) -> &mut Self {
+        LatexBuilder::add_raw(self, raw)
+    }
+
+    fn add_comment(&mut self, text: &str) -> &mut Self {
+        LatexBuilder::add_comment(self, text)
+    }
+
+    fn add_include(&mut self, path: &str) -> &mut Self {
+        LatexBuilder::add_simple_command(self, "input", path)
+    }
+
+    fn add_page_break(&mut self) -> &mut Self {
+        LatexBuilder::add_command(self, "newpage", &[])
+    }
+
+    fn build(&self) -> String {
+        LatexBuilder::build(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        LatexBuilder::is_empty(self)
     }
+
+    fn bold(text: &str) -> String {
+        format!("\\textbf{{{text}}}")
+    }
+
+    fn italic(text: &str) -> String {
+        format!("\\emph{{{text}}}")
+    }
+
+    fn code(text: &str) -> String {
+        format!("\\texttt{{{}}}", sanitize_latex(text))
+    }
+}
+
+/// Escapes `url` for `\url` in the argument of another command, where only
+/// `%`, `#` and the characters that would unbalance the argument need care.
+pub fn sanitize_url(url: &str) -> String {
+    let mut output = String::with_capacity(url.len());
+    for c in url.trim().chars() {
+        match c {
+            '%' => output.push_str("\\%"),
+            '#' => output.push_str("\\#"),
+            '\\' => output.push_str("\\%5C"),
+            '{' => output.push_str("\\%7B"),
+            '}' => output.push_str("\\%7D"),
+            c => output.push(c),
+        }
+    }
+    output
 }
 
 pub fn sanitize_latex(input: &str) -> String {
-    input
-        .replace('&', "\\&")
-        .replace('%', "\\%")
-        .replace('$', "\\$")
-        .replace('#', "\\#")
-        .replace('°', "\\textdegree{}")
+    // Escape character by character so the replacements introduced for one
+    // special character (e.g. the braces in `\textbackslash{}`) are never
+    // escaped again by another rule.
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '\\' => output.push_str("\\textbackslash{}"),
+            '&' => output.push_str("\\&"),
+            '%' => output.push_str("\\%"),
+            '$' => output.push_str("\\$"),
+            '#' => output.push_str("\\#"),
+            '_' => output.push_str("\\_"),
+            '{' => output.push_str("\\{"),
+            '}' => output.push_str("\\}"),
+            '~' => output.push_str("\\textasciitilde{}"),
+            '^' => output.push_str("\\textasciicircum{}"),
+            '°' => output.push_str("\\textdegree{}"),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_latex_escapes_every_special_character_once() {
+        assert_eq!(
+            sanitize_latex(r"a\b & 50% $3 #1 x_2 {y} ~z 2^3"),
+            r"a\textbackslash{}b \& 50\% \$3 \#1 x\_2 \{y\} \textasciitilde{}z 2\textasciicircum{}3"
+        );
+    }
+
+    #[test]
+    fn sanitize_latex_keeps_plain_text() {
+        assert_eq!(
+            sanitize_latex("Extra-virgin olive oil"),
+            "Extra-virgin olive oil"
+        );
+    }
+
+    #[test]
+    fn environment_arguments_follow_begin() {
+        let mut content = LatexBuilder::new();
+        content.add_simple_command("step", "Mix.");
+
+        let mut latex = LatexBuilder::new();
+        latex.add_env_with_args(
+            "recipe",
+            &[Arg::optional("twocolumn"), Arg::required("Pancakes")],
+            &content,
+        );
+        assert_eq!(
+            latex.build(),
+            "\\begin{recipe}[twocolumn]{Pancakes}\n  \\step{Mix.}\n\\end{recipe}"
+        );
+    }
+
+    #[test]
+    fn nested_environments_are_indented() {
+        let mut ingredients = LatexBuilder::new();
+        ingredients.add_simple_command("ingredient", "200 g flour");
+
+        let mut recipe = LatexBuilder::new();
+        recipe
+            .add_simple_command("recipeheader", "Pancakes")
+            .add_env("ingredients", &ingredients);
+
+        let mut latex = LatexBuilder::new();
+        latex.add_env("recipe", &recipe);
+        let expected = [
+            r"\begin{recipe}",
+            r"  \recipeheader{Pancakes}",
+            r"  \begin{ingredients}",
+            r"    \ingredient{200 g flour}",
+            r"  \end{ingredients}",
+            r"\end{recipe}",
+        ];
+        assert_eq!(latex.build(), expected.join("\n"));
+    }
+
+    #[test]
+    fn raw_latex_is_added_unmodified() {
+        let mut latex = LatexBuilder::new();
+        latex
+            .add_simple_command("step", "Mix.")
+            .add_raw("\\clearpage");
+        assert_eq!(latex.build(), "\\step{Mix.}\n\\clearpage");
+    }
+
+    #[test]
+    fn comments_get_one_line_per_line_of_text() {
+        let mut latex = LatexBuilder::new();
+        latex.add_comment("Generated from pancakes.cook\nDo not edit");
+        assert_eq!(
+            latex.build(),
+            "% Generated from pancakes.cook\n% Do not edit"
+        );
+    }
+
+    #[test]
+    fn validate_reports_unbalanced_braces() {
+        let mut unclosed = LatexBuilder::new();
+        unclosed
+            .add_simple_command("step", "Mix.")
+            .add_raw("\\textbf{Mix.");
+        assert_eq!(unclosed.validate(), ["Unclosed { on line 2"]);
+
+        let mut unmatched = LatexBuilder::new();
+        unmatched.add_raw("\\step{Mix.}}");
+        assert_eq!(unmatched.validate(), ["Unmatched } on line 1"]);
+    }
+
+    #[test]
+    fn validate_reports_mismatched_environments() {
+        let mut latex = LatexBuilder::new();
+        latex
+            .add_raw("\\begin{ingredients}")
+            .add_raw("\\end{steps}");
+        assert_eq!(
+            latex.validate(),
+            [r"\end{steps} on line 2 closes \begin{ingredients} from line 1"]
+        );
+    }
+
+    #[test]
+    fn validate_ignores_escaped_braces_and_comments() {
+        let mut latex = LatexBuilder::new();
+        latex
+            .add_simple_command("step", &sanitize_latex("Use {a} bowl"))
+            .add_comment("Unbalanced { in a comment");
+        assert!(latex.validate().is_empty(), "{:?}", latex.validate());
+    }
+
+    #[test]
+    fn build_checked_fails_on_an_unclosed_environment() {
+        let mut latex = LatexBuilder::new();
+        latex
+            .add_raw("\\begin{x}")
+            .add_simple_command("step", "Mix.");
+
+        let error = latex.build_checked().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r"Malformed LaTeX: \begin{x} on line 1 is never closed"
+        );
+    }
+
+    #[test]
+    fn build_checked_accepts_nested_environments() {
+        let mut steps = LatexBuilder::new();
+        steps.add_simple_command("step", "Mix.");
+        let mut latex = LatexBuilder::new();
+        latex.add_env("instructions", &steps);
+
+        assert_eq!(latex.build_checked().unwrap(), latex.build());
+    }
+
+    #[test]
+    fn build_checked_reports_mismatched_environments() {
+        let mut inner = LatexBuilder::new();
+        inner.add_raw("Mix.\n\\end{steps}");
+        let mut latex = LatexBuilder::new();
+        latex.add_raw("\\begin{ingredients}").add_builder(&inner);
+
+        let error = latex.build_checked().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r"Malformed LaTeX: \end{steps} on line 3 closes \begin{ingredients} from line 1"
+        );
+    }
+
+    #[test]
+    fn build_checked_ignores_braces() {
+        let mut latex = LatexBuilder::new();
+        latex.add_raw("\\textbf{");
+
+        assert_eq!(latex.build_checked().unwrap(), "\\textbf{");
+    }
 }
@@ -0,0 +1,35 @@
+//! Transpiles Cooklang recipes to LaTeX or Typst.
+//!
+//! The main entry point is [`RecipeTranspiler`], which transpiles every
+//! recipe of a collection folder. [`create_recipe`] builds the LaTeX of a
+//! single parsed recipe and [`LatexBuilder`] is the builder used for all
+//! generated LaTeX. Both are rendered by [`render_recipe`], which Typst
+//! output uses with [`TypstBuilder`].
+
+pub mod cache;
+pub mod collection;
+pub mod format;
+pub mod images;
+pub mod index;
+pub mod io;
+mod json;
+pub mod latex;
+pub mod main_file;
+pub mod pdf;
+pub mod recipe;
+pub mod render;
+pub mod shopping;
+pub mod standalone;
+#[cfg(test)]
+mod testing;
+pub mod typst;
+
+pub use format::{FormatOptions, TimeLabels};
+pub use json::recipe_json_schema;
+pub use latex::{sanitize_latex, sanitize_url, validate_latex, Arg, LatexBuilder};
+pub use recipe::{
+    create_recipe, render_recipe, transpile_recipe_str, EmitFormat, Numbering, OutputFormat,
+    RecipeLocation, RecipeTranspiler, TranspileOptions, TranspiledCollection, TranspiledRecipe,
+};
+pub use render::RecipeRenderer;
+pub use typst::TypstBuilder;
@@ -1,55 +1,567 @@
 mod cli;
-mod io;
-mod latex;
-mod recipe;
 
-use anyhow::{Context, Result};
-use clap::Parser;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
 
-use crate::latex::sanitize_latex;
+use anyhow::{Context, Result};
+use cooklang::convert::System;
+use cooklatex::{
+    cache::BuildCache,
+    collection, format, index, io, main_file, pdf,
+    recipe::{self, OutputFormat, RecipeTranspiler, TranspiledCollection, TranspiledRecipe},
+    standalone, LatexBuilder, RecipeRenderer, TypstBuilder,
+};
+use log::{Level, LevelFilter};
+use rayon::prelude::*;
 
 fn main() -> Result<()> {
-    let cli = cli::Cli::parse();
+    let cli = cli::Cli::parse_with_config()?;
+
+    if let Some(cli::Command::ListSystems) = cli.command {
+        for system in [System::Metric, System::Imperial] {
+            println!("{}", cli::system_name(system));
+        }
+        return Ok(());
+    }
 
-    let latex_dir = &cli.latex_dir;
-    let output_dir = &cli.latex_out_dir;
+    if cli.print_schema {
+        println!("{}", cooklatex::recipe_json_schema()?);
+        return Ok(());
+    }
+
+    init_logger(cli.log_level());
+    io::set_dry_run(cli.dry_run);
+
+    if cli.open && !cli.pdf {
+        log::warn!("--open has no effect without --pdf");
+    }
 
-    let units_file = if let Some(units_file) = &cli.units_file {
+    let output_dir = &cli.output_dir();
+
+    if let Some(jobs) = cli.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .context("Failed to configure thread pool")?;
+    }
+
+    let units_file = if let Some(units_file) = &cli.units_file() {
         let text = std::fs::read_to_string(units_file)
             .with_context(|| format!("Cannot find units file: {}", units_file.display()))?;
-        let units = toml::from_str(&text)?;
+        let units = toml::from_str(&text).context("Failed to load units file")?;
         Some(units)
     } else {
         None
     };
 
-    io::clone_folder_to_target(latex_dir, output_dir).context("Failed to clone LaTeX directory")?;
+    let emit_latex = cli.emit.contains(&recipe::EmitFormat::Latex);
+
+    if let Some(latex_dir) = &cli.latex_dir {
+        if cli.clean && same_dir(latex_dir, output_dir) {
+            anyhow::bail!(
+                "Refusing to clean the template folder {}",
+                latex_dir.display()
+            );
+        }
+        if !cli.force && !cli.dry_run && !BuildCache::exists(output_dir) {
+            io::ensure_empty_dir(output_dir)?;
+        }
+        if cli.clean {
+            clean_output_dir(latex_dir, output_dir)?;
+        }
+
+        if emit_latex {
+            io::clone_folder_to_target(latex_dir, output_dir)
+                .context("Failed to clone LaTeX directory")?;
+        }
+    }
+
+    let time_labels = if let Some(time_labels) = &cli.time_labels {
+        let text = std::fs::read_to_string(time_labels)
+            .with_context(|| format!("Cannot find time labels file: {}", time_labels.display()))?;
+        toml::from_str(&text).context("Invalid time labels file")?
+    } else {
+        format::TimeLabels::default()
+    };
+
+    let unit_labels = if let Some(unit_labels) = &cli.unit_labels {
+        let text = std::fs::read_to_string(unit_labels)
+            .with_context(|| format!("Cannot find unit labels file: {}", unit_labels.display()))?;
+        toml::from_str(&text).context("Invalid unit labels file")?
+    } else {
+        HashMap::new()
+    };
 
-    let transpiler = recipe::RecipeTranspiler::new(cli.convert, output_dir, units_file);
-    let mut latex = latex::LatexBuilder::new();
+    let mut layouts = format::default_layouts();
+    if let Some(layouts_file) = &cli.layouts {
+        let text = std::fs::read_to_string(layouts_file)
+            .with_context(|| format!("Cannot find layouts file: {}", layouts_file.display()))?;
+        let custom: HashMap<String, String> =
+            toml::from_str(&text).context("Invalid layouts file")?;
+        layouts.extend(custom);
+    }
 
-    for collection in &cli.collections {
-        let collection_path = collection;
-        let collection_name = recipe::get_collection_name(collection_path)?;
+    let format = format::FormatOptions {
+        fractions: cli.fractions,
+        round: cli.round,
+        time_labels,
+        step_notes: cli.step_notes,
+        merge_ingredients: cli.merge_ingredients,
+        ingredient_sort: cli.ingredient_sort,
+        ingredient_columns: cli.ingredient_columns,
+        normalize_units: cli.normalize_units,
+        always_sections: cli.always_sections,
+        decimal_separator: cli.decimal_separator,
+        thousands_separator: cli.thousands_separator,
+        unit_labels,
+        split_ingredient_args: cli.split_ingredient_args,
+        timer_commands: cli.timer_commands,
+        step_line_breaks: cli.step_line_breaks,
+        extra_meta_keys: cli.meta_key.clone(),
+        layouts,
+        require_servings: !cli.stdin,
+        title_from_filename: cli.title_from_filename || cli.stdin,
+    };
+
+    let options = recipe::TranspileOptions {
+        convert_systems: cli.convert.clone(),
+        convert_strict: cli.convert_strict,
+        scale: cli.scale,
+        scale_timers: cli.scale_timers,
+        servings: cli.servings,
+        format,
+        emit: cli.emit.clone(),
+        output_format: cli.output_format,
+        max_image_width: cli.max_image_width,
+        inline: cli.standalone.is_some(),
+        filter_tags: cli.filter_tag.clone(),
+        exclude_tags: cli.exclude_tag.clone(),
+        parser_warnings: !cli.no_warnings,
+        numbering: cli.number_recipes,
+        cache: !cli.no_cache,
+        shopping_list: cli.shopping_list.is_some(),
+    };
+
+    if cli.stdin {
+        let mut contents = String::new();
+        std::io::stdin()
+            .read_to_string(&mut contents)
+            .context("Failed to read recipe from stdin")?;
+        print!(
+            "{}",
+            recipe::transpile_recipe_str(&contents, &options, units_file)?
+        );
+        return Ok(());
+    }
 
-        latex.add_simple_command("chapter", &sanitize_latex(&collection_name));
+    let collections = match &cli.collections_root {
+        Some(root) if cli.collections.is_empty() => {
+            collection::find_collections(root, cli.max_depth)
+                .with_context(|| format!("Failed to find collections in {}", root.display()))?
+        }
+        _ => unique_collections(&cli.collections),
+    };
+    let transpiler = RecipeTranspiler::new(options, output_dir, units_file)?;
+    let (mut body, transpiled) = match cli.output_format {
+        OutputFormat::Latex => transpile_collections::<LatexBuilder>(
+            &transpiler,
+            &collections,
+            cli.output_format,
+            cli.max_depth,
+            cli.number_recipes.is_some(),
+        )?,
+        OutputFormat::Typst => transpile_collections::<TypstBuilder>(
+            &transpiler,
+            &collections,
+            cli.output_format,
+            cli.max_depth,
+            cli.number_recipes.is_some(),
+        )?,
+    };
 
-        match transpiler.transpile_collection(collection_path) {
-            Ok(recipe_files) => {
-                let mut iter = recipe_files.iter().peekable();
-                while let Some(recipe_file) = iter.next() {
-                    latex.add_simple_command("input", recipe_file);
+    transpiler.save_cache()?;
+
+    warn_missing_references(&transpiled.recipes);
+    print_summary(&transpiled, collections.len());
+
+    if cli.strict && !(transpiled.failed.is_empty() && transpiled.failed_collections.is_empty()) {
+        anyhow::bail!(
+            "{} recipes and {} collections failed to transpile",
+            transpiled.failed.len(),
+            transpiled.failed_collections.len()
+        );
+    }
+
+    let recipes = transpiled.recipes;
+
+    if let Some(shopping_list) = &cli.shopping_list {
+        io::write_file(shopping_list, &transpiler.shopping_list(&recipes).build())
+            .context("Failed to write shopping list")?;
+    }
+
+    let preambles = collections
+        .iter()
+        .map(|collection| collection::get_collection_preamble(collection, cli.output_format))
+        .collect::<Result<Vec<_>>>()?;
+    let preamble = preambles
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if !emit_latex {
+        return Ok(());
+    }
+
+    let index = cli
+        .index
+        .then(|| index::build_ingredient_index(&recipes).build());
+
+    if let Some(standalone) = &cli.standalone {
+        if let Some(index) = &index {
+            body = format!("{body}\n{index}");
+        }
+
+        return io::write_file(
+            standalone,
+            &standalone::document(&preamble, &body, cli.split_ingredient_args),
+        )
+        .context("Failed to write standalone document");
+    }
+
+    if cli.dry_run {
+        println!("{body}");
+        if let Some(index) = index {
+            println!("{index}");
+        }
+        return Ok(());
+    }
+
+    main_file::replace_in_main_file(output_dir, cli.output_format, &cli.placeholder, &body)
+        .context("Failed to replace in main file")?;
+
+    if !preamble.is_empty() {
+        main_file::replace_in_main_file(
+            output_dir,
+            cli.output_format,
+            main_file::PREAMBLE_PLACEHOLDER,
+            &preamble,
+        )
+        .context("Failed to insert collection preambles in main file")?;
+    }
+
+    if let Some(index) = index {
+        main_file::replace_in_main_file(
+            output_dir,
+            cli.output_format,
+            index::INDEX_PLACEHOLDER,
+            &index,
+        )
+        .context("Failed to insert ingredient index in main.tex")?;
+    }
+
+    if cli.vars.is_some() || cli.book_meta.is_some() {
+        let mut vars = HashMap::new();
+
+        if let Some(book_meta) = &cli.book_meta {
+            let text = std::fs::read_to_string(book_meta).with_context(|| {
+                format!("Cannot find book metadata file: {}", book_meta.display())
+            })?;
+            let book_meta: main_file::BookMeta =
+                toml::from_str(&text).context("Invalid book metadata file")?;
+            vars.extend(book_meta.vars(cli.output_format));
+        }
+
+        if let Some(vars_file) = &cli.vars {
+            let text = std::fs::read_to_string(vars_file).with_context(|| {
+                format!(
+                    "Cannot find template variables file: {}",
+                    vars_file.display()
+                )
+            })?;
+            let custom: HashMap<String, String> =
+                toml::from_str(&text).context("Invalid template variables file")?;
+            vars.extend(custom);
+        }
+
+        main_file::replace_vars_in_main_file(output_dir, cli.output_format, &vars)
+            .context("Failed to substitute template variables")?;
+    }
+
+    if cli.pdf {
+        pdf::compile_pdf(output_dir, cli.engine).context("Failed to compile PDF")?;
+
+        if cli.open {
+            if let Err(e) = pdf::open_pdf(output_dir) {
+                log::warn!("{e:#}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn init_logger(level: LevelFilter) {
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format(|buf, record| match record.level() {
+            Level::Warn => writeln!(buf, "Warning: {}", record.args()),
+            Level::Error => writeln!(buf, "Error: {}", record.args()),
+            _ => writeln!(buf, "{}", record.args()),
+        })
+        .parse_default_env()
+        .init();
+}
+
+/// Removes collections that were given more than once, keeping the first
+/// occurrence so the chapters follow the order of the arguments.
+fn unique_collections(collections: &[PathBuf]) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+
+    collections
+        .iter()
+        .filter(|collection| {
+            let key =
+                std::fs::canonicalize(collection).unwrap_or_else(|_| collection.to_path_buf());
+            let first = seen.insert(key);
+            if !first {
+                log::warn!(
+                    "Collection {} was given more than once, skipping it",
+                    collection.display()
+                );
+            }
+            first
+        })
+        .cloned()
+        .collect()
+}
+
+/// Transpiles every collection into a chapter, returning the body that replaces
+/// the placeholder of the main file. The collections are transpiled in parallel
+/// unless `ordered` is set, e.g. when recipes are numbered in the order they
+/// are transpiled, but the chapters are always in the order of `collections`.
+fn transpile_collections<R: RecipeRenderer>(
+    transpiler: &RecipeTranspiler,
+    collections: &[PathBuf],
+    format: OutputFormat,
+    max_depth: usize,
+    ordered: bool,
+) -> Result<(String, TranspiledCollection)> {
+    let transpile = |collection_path: &PathBuf| {
+        transpile_chapter::<R>(transpiler, collection_path, format, max_depth)
+    };
+    let chapters: Vec<_> = if ordered {
+        collections.iter().map(transpile).collect()
+    } else {
+        collections.par_iter().map(transpile).collect()
+    };
+
+    let mut body = R::default();
+    let mut transpiled = TranspiledCollection::default();
+    for chapter in chapters {
+        let (chapter, collection) = chapter?;
+        if let Some(chapter) = chapter {
+            body.add_raw(&chapter);
+        }
+        transpiled.recipes.extend(collection.recipes);
+        transpiled.failed.extend(collection.failed);
+        transpiled
+            .failed_collections
+            .extend(collection.failed_collections);
+    }
+
+    Ok((body.build(), transpiled))
+}
+
+/// Transpiles a single collection into its own chapter, collections without
+/// a single transpiled recipe get no chapter.
+fn transpile_chapter<R: RecipeRenderer>(
+    transpiler: &RecipeTranspiler,
+    collection_path: &Path,
+    format: OutputFormat,
+    max_depth: usize,
+) -> Result<(Option<String>, TranspiledCollection)> {
+    let collection_name = collection::get_collection_name(collection_path)?;
+
+    let mut recipes = R::default();
+    let mut transpiled = TranspiledCollection::default();
+    transpiler.start_collection();
+    add_collection_folder(
+        &mut recipes,
+        &mut transpiled,
+        transpiler,
+        collection_path,
+        &collection_name,
+        0,
+        max_depth,
+    )?;
+
+    if transpiled.recipes.is_empty() {
+        return Ok((None, transpiled));
+    }
+
+    let mut chapter = R::default();
+    chapter.add_simple_command("chapter", &R::escape(&collection_name));
+
+    if let Some(intro) = collection::get_collection_intro::<R>(collection_path, format)? {
+        chapter.add_raw(&intro);
+    }
+
+    chapter.add_raw(&recipes.build());
+    Ok((Some(chapter.build()), transpiled))
+}
+
+/// The headings of nested collection folders, by nesting depth.
+const SECTION_COMMANDS: [&str; 3] = ["section", "subsection", "subsubsection"];
+
+/// Adds the recipes of a collection folder, followed by its subfolders
+/// containing recipes, each under a heading, up to `max_depth` levels deep.
+fn add_collection_folder<R: RecipeRenderer>(
+    body: &mut R,
+    transpiled: &mut TranspiledCollection,
+    transpiler: &RecipeTranspiler,
+    folder: &Path,
+    collection_name: &str,
+    depth: usize,
+    max_depth: usize,
+) -> Result<()> {
+    let subfolders = if depth < max_depth {
+        collection::find_collections(folder, max_depth - depth - 1)?
+    } else {
+        Vec::new()
+    };
+
+    let mut has_content = false;
+
+    // A collection may hold nothing but nested folders
+    if subfolders.is_empty() || collection::has_recipe_files(folder)? {
+        match transpiler.transpile_folder(folder, collection_name) {
+            Ok(collection) => {
+                let mut iter = collection.recipes.iter().peekable();
+                while let Some(recipe) = iter.next() {
+                    match &recipe.content {
+                        Some(content) => body.add_raw(content),
+                        None => body.add_include(&recipe.path),
+                    };
                     if iter.peek().is_some() {
-                        latex.add_command("newpage", &Vec::new());
+                        body.add_page_break();
                     }
                 }
+                has_content = !collection.recipes.is_empty();
+                transpiled.recipes.extend(collection.recipes);
+                transpiled.failed.extend(collection.failed);
+            }
+            Err(e) => {
+                log::warn!("Failed to process collection {collection_name}: {e}");
+                transpiled.failed_collections.push(folder.to_path_buf());
             }
-            Err(e) => eprintln!("Warning: Failed to process collection {collection_name}: {e}"),
         }
     }
 
-    recipe::replace_in_main_tex(output_dir, &latex.build())
-        .context("Failed to replace in main.tex")?;
+    for subfolder in subfolders {
+        let folder_name = collection::get_collection_name(&subfolder)?;
+        let heading = SECTION_COMMANDS[depth.min(SECTION_COMMANDS.len() - 1)];
+
+        if has_content {
+            body.add_page_break();
+        }
+        body.add_simple_command(heading, &R::escape(&folder_name));
+
+        add_collection_folder(
+            body,
+            transpiled,
+            transpiler,
+            &subfolder,
+            &format!("{collection_name}/{folder_name}"),
+            depth + 1,
+            max_depth,
+        )?;
+        has_content = true;
+    }
 
     Ok(())
 }
+
+/// Removes the files an earlier run wrote to `output_dir`: the recipe files
+/// recorded in its cache, the copies of the template files and the cache
+/// itself. Anything else in the folder is kept.
+fn clean_output_dir(template_dir: &Path, output_dir: &Path) -> Result<()> {
+    let cache = BuildCache::load(output_dir);
+    for file in cache.previous_files() {
+        io::remove_file(&output_dir.join(file))?;
+    }
+
+    for file in io::list_dir(template_dir)? {
+        let name = file.file_name().context("Invalid template file name")?;
+        io::remove_file(&output_dir.join(name))?;
+    }
+
+    io::remove_file(cache.path())
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn print_summary(transpiled: &TranspiledCollection, collections: usize) {
+    if !log::log_enabled!(Level::Warn) {
+        return;
+    }
+
+    let TranspiledCollection {
+        recipes,
+        failed,
+        failed_collections,
+    } = transpiled;
+
+    eprintln!(
+        "Processed {} recipes across {collections} collections ({} failed)",
+        recipes.len() + failed.len(),
+        failed.len()
+    );
+    for path in failed {
+        eprintln!("  {}", path.display());
+    }
+
+    if !failed_collections.is_empty() {
+        eprintln!("{} collections failed", failed_collections.len());
+        for path in failed_collections {
+            eprintln!("  {}", path.display());
+        }
+    }
+
+    let unconverted: Vec<_> = recipes
+        .iter()
+        .filter(|recipe| recipe.conversion_errors > 0)
+        .collect();
+    if !unconverted.is_empty() {
+        eprintln!(
+            "{} recipes have quantities that could not be converted",
+            unconverted.len()
+        );
+        for recipe in unconverted {
+            eprintln!("  {} ({})", recipe.path, recipe.conversion_errors);
+        }
+    }
+}
+
+fn warn_missing_references(recipes: &[TranspiledRecipe]) {
+    let labels: HashSet<&str> = recipes.iter().map(|recipe| recipe.label.as_str()).collect();
+
+    for recipe in recipes {
+        for reference in &recipe.references {
+            if !labels.contains(reference.as_str()) {
+                log::warn!(
+                    "Recipe {} references {reference}, which was not transpiled",
+                    recipe.path
+                );
+            }
+        }
+    }
+}
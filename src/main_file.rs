@@ -0,0 +1,163 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    index::INDEX_PLACEHOLDER, io, latex::sanitize_latex, recipe::OutputFormat,
+    typst::sanitize_typst,
+};
+use anyhow::Result;
+use serde::Deserialize;
+
+pub const PREAMBLE_PLACEHOLDER: &str = "%{{preamble}}";
+
+/// The metadata of the cookbook itself, read with `--book-meta`. Every field
+/// fills the `%{{book_<field>}}` placeholder of the main file, e.g.
+/// `%{{book_title}}`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BookMeta {
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub author: Option<String>,
+    pub date: Option<String>,
+}
+
+impl BookMeta {
+    /// The placeholder values of the fields that are set, escaped for
+    /// `format` as they are plain text.
+    pub fn vars(&self, format: OutputFormat) -> HashMap<String, String> {
+        let escape = match format {
+            OutputFormat::Latex => sanitize_latex,
+            OutputFormat::Typst => sanitize_typst,
+        };
+
+        [
+            ("title", &self.title),
+            ("subtitle", &self.subtitle),
+            ("author", &self.author),
+            ("date", &self.date),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| Some((format!("book_{field}"), escape(value.as_deref()?))))
+        .collect()
+    }
+}
+
+/// Replaces the `%{{key}}` placeholder of every variable in the main file with
+/// its value, inserted as is. Variables the main file doesn't use are ignored,
+/// placeholders left without a value are warned about.
+pub fn replace_vars_in_main_file(
+    out_dir: &Path,
+    format: OutputFormat,
+    vars: &HashMap<String, String>,
+) -> Result<()> {
+    let main_file = out_dir.join(format!("main.{}", format.extension()));
+
+    let mut contents = io::read_file(&main_file)?;
+    for (key, value) in vars {
+        contents = contents.replace(&format!("%{{{{{key}}}}}"), value);
+    }
+
+    let known = [INDEX_PLACEHOLDER, PREAMBLE_PLACEHOLDER];
+    let mut rest = contents.as_str();
+    while let Some(start) = rest.find("%{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        let placeholder = &rest[start..start + end + 2];
+        if !known.contains(&placeholder) {
+            log::warn!(
+                "Placeholder {placeholder} in {} has no value",
+                main_file.display()
+            );
+        }
+        rest = &rest[start + end + 2..];
+    }
+
+    io::write_file(&main_file, &contents)
+}
+
+/// Replaces `placeholder` in the main file of the template, `main.tex` or
+/// `main.typ` depending on the output format.
+pub fn replace_in_main_file(
+    out_dir: &Path,
+    format: OutputFormat,
+    placeholder: &str,
+    new_content: &str,
+) -> Result<()> {
+    let main_file = out_dir.join(format!("main.{}", format.extension()));
+
+    let main_contents = io::read_file(&main_file)?;
+    if !main_contents.contains(placeholder) {
+        anyhow::bail!(
+            "Placeholder {placeholder} was not found in {}",
+            main_file.display()
+        );
+    }
+
+    let new_contents = main_contents.replace(placeholder, new_content);
+
+    io::write_file(&main_file, &new_contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_placeholder_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.tex"), "%{{recipes}}").unwrap();
+
+        let error = replace_in_main_file(dir.path(), OutputFormat::Latex, "%{{body}}", "")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("Placeholder %{{body}} was not found"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn placeholder_is_replaced_in_main_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let main_file = dir.path().join("main.tex");
+        std::fs::write(&main_file, "before\n%{{body}}\nafter").unwrap();
+
+        replace_in_main_file(dir.path(), OutputFormat::Latex, "%{{body}}", r"\input{a}").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(main_file).unwrap(),
+            "before\n\\input{a}\nafter"
+        );
+    }
+
+    #[test]
+    fn template_vars_are_substituted() {
+        let dir = tempfile::tempdir().unwrap();
+        let main_file = dir.path().join("main.tex");
+        std::fs::write(&main_file, "\\title{%{{title}}}\n%{{author}}").unwrap();
+
+        let vars = HashMap::from([
+            ("title".to_string(), "Family Favourites".to_string()),
+            ("unused".to_string(), "ignored".to_string()),
+        ]);
+        replace_vars_in_main_file(dir.path(), OutputFormat::Latex, &vars).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(main_file).unwrap(),
+            "\\title{Family Favourites}\n%{{author}}"
+        );
+    }
+
+    #[test]
+    fn book_meta_vars_are_escaped_and_prefixed() {
+        let meta = BookMeta {
+            title: Some("Mom & Dad's Kitchen".to_string()),
+            author: Some("The Smiths".to_string()),
+            ..BookMeta::default()
+        };
+
+        let vars = meta.vars(OutputFormat::Latex);
+        assert_eq!(vars.len(), 2, "{vars:?}");
+        assert_eq!(vars["book_title"], r"Mom \& Dad's Kitchen");
+        assert_eq!(vars["book_author"], "The Smiths");
+    }
+}
@@ -0,0 +1,54 @@
+use std::{io::ErrorKind, path::Path, process::Command};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+
+/// The LaTeX engine latexmk compiles with. XeLaTeX is the default, as the
+/// example template selects its fonts with `fontspec`, which pdfLaTeX lacks.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum Engine {
+    Pdflatex,
+    #[default]
+    Xelatex,
+    Lualatex,
+}
+
+impl Engine {
+    fn latexmk_flag(self) -> &'static str {
+        match self {
+            Engine::Pdflatex => "-pdf",
+            Engine::Xelatex => "-xelatex",
+            Engine::Lualatex => "-lualatex",
+        }
+    }
+}
+
+pub fn compile_pdf(out_dir: &Path, engine: Engine) -> Result<()> {
+    let status = Command::new("latexmk")
+        .arg(engine.latexmk_flag())
+        .arg("-interaction=nonstopmode")
+        .arg("main.tex")
+        .current_dir(out_dir)
+        .status()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => {
+                anyhow::anyhow!(
+                    "latexmk was not found, make sure a LaTeX distribution is installed"
+                )
+            }
+            _ => anyhow::Error::new(e),
+        })
+        .context("Failed to run latexmk")?;
+
+    if !status.success() {
+        bail!("latexmk failed with {status}");
+    }
+
+    Ok(())
+}
+
+/// Opens the compiled `main.pdf` in the default PDF viewer of the system.
+pub fn open_pdf(out_dir: &Path) -> Result<()> {
+    let pdf = out_dir.join("main.pdf");
+    opener::open(&pdf).with_context(|| format!("Failed to open {}", pdf.display()))
+}
@@ -1,79 +1,297 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashSet,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use crate::{
-    io,
-    latex::{sanitize_latex, Arg, LatexBuilder},
+    cache::BuildCache,
+    collection::{get_collection_name, order_recipe_files},
+    format::{format_number, format_quantity, FormatOptions, IngredientSort, TimeLabels},
+    images::{copy_recipe_image, get_recipe_image},
+    io, json,
+    latex::{validate_latex, Arg, LatexBuilder},
+    render::{render_inline, RecipeRenderer},
+    shopping::build_shopping_list,
+    typst::TypstBuilder,
 };
 use anyhow::{Context, Result};
+use clap::ValueEnum;
 use cooklang::{
     convert::{ConverterBuilder, System, UnitsFile},
     ingredient_list::GroupedIngredient,
     metadata::StdKey,
-    Content, Converter, CooklangParser, Extensions, GroupedQuantity, Ingredient, Item, Metadata,
-    Quantity, Recipe, Step,
+    quantity::{Number, Value},
+    Content, Converter, CooklangParser, Cookware, Extensions, GroupedQuantity, Ingredient, Item,
+    Metadata, Quantity, Recipe, RecipeReference, Step,
 };
+use indexmap::IndexMap;
+use rayon::prelude::*;
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmitFormat {
+    Latex,
+    Json,
+}
+
+/// The markup language recipes are written in.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Latex,
+    Typst,
+}
+
+impl OutputFormat {
+    /// The extension of the generated recipe files and the main file.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Latex => "tex",
+            OutputFormat::Typst => "typ",
+        }
+    }
+}
+
+/// Where the numbering of recipe titles restarts.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Numbering {
+    /// Restart at 1 in every collection
+    Collection,
+    /// Count on across all collections
+    Global,
+}
+
+/// A recipe that was successfully written to the output directory.
+#[derive(Debug)]
+pub struct TranspiledRecipe {
+    /// Path of the generated file, relative to the output directory
+    pub path: String,
+    pub title: String,
+    /// The collection the recipe is in, e.g. `Desserts/Cakes`
+    pub collection: String,
+    /// The LaTeX label assigned to the recipe, see [`recipe_label`]
+    pub label: String,
+    /// Labels of the recipes referenced as ingredients
+    pub references: Vec<String>,
+    /// Names of the ingredients shown in the recipe's ingredient list
+    pub ingredients: Vec<String>,
+    /// The ingredients to buy with their summed quantities, only collected
+    /// for a shopping list
+    pub shopping: Vec<(String, GroupedQuantity)>,
+    /// The generated LaTeX, only kept when transpiling inline
+    pub content: Option<String>,
+    /// The number of quantities that could not be converted
+    pub conversion_errors: usize,
+}
+
+/// The recipes of a collection, or of all collections once combined.
+#[derive(Debug, Default)]
+pub struct TranspiledCollection {
+    pub recipes: Vec<TranspiledRecipe>,
+    /// Paths of the recipe files that failed to transpile
+    pub failed: Vec<PathBuf>,
+    /// Paths of the collection folders that failed as a whole, e.g. because
+    /// they could not be read
+    pub failed_collections: Vec<PathBuf>,
+}
+
+/// Options controlling how recipes are transpiled.
+#[derive(Debug)]
+pub struct TranspileOptions {
+    /// Systems to convert to, quantities of all but the first are shown in parentheses
+    pub convert_systems: Vec<System>,
+    /// Fail recipes with quantities that could not be converted
+    pub convert_strict: bool,
+    pub scale: f64,
+    /// Also scale the durations of timers
+    pub scale_timers: bool,
+    /// Scale every recipe to this many servings, overrides `scale`
+    pub servings: Option<u32>,
+    pub format: FormatOptions,
+    pub emit: Vec<EmitFormat>,
+    /// The markup language of the recipes emitted with `EmitFormat::Latex`
+    pub output_format: OutputFormat,
+    /// Scale copied recipe images down to at most this many pixels wide
+    pub max_image_width: Option<u32>,
+    /// Keep the LaTeX of every recipe in memory instead of writing a file per recipe
+    pub inline: bool,
+    /// Only include recipes having all of these tags
+    pub filter_tags: Vec<String>,
+    /// Leave out recipes having any of these tags
+    pub exclude_tags: Vec<String>,
+    /// Print the parser warnings of every recipe, errors are always printed
+    pub parser_warnings: bool,
+    /// Prefix recipe titles with their number
+    pub numbering: Option<Numbering>,
+    /// Skip writing recipe files whose content is unchanged since the last run
+    pub cache: bool,
+    /// Collect the quantities of every recipe for a shopping list
+    pub shopping_list: bool,
+}
+
+impl Default for TranspileOptions {
+    fn default() -> Self {
+        Self {
+            convert_systems: Vec::new(),
+            convert_strict: false,
+            scale: 1.0,
+            scale_timers: false,
+            servings: None,
+            format: FormatOptions::default(),
+            emit: vec![EmitFormat::Latex],
+            output_format: OutputFormat::default(),
+            max_image_width: None,
+            inline: false,
+            filter_tags: Vec::new(),
+            exclude_tags: Vec::new(),
+            parser_warnings: true,
+            numbering: None,
+            cache: true,
+            shopping_list: false,
+        }
+    }
+}
+
+impl TranspileOptions {
+    /// Converting or normalizing quantities needs the bundled units, even
+    /// without a units file.
+    fn uses_bundled_units(&self) -> bool {
+        !self.convert_systems.is_empty() || self.format.normalize_units
+    }
+}
+
+/// Transpiles collections of recipes into `output_dir`.
 #[derive(Debug)]
 pub struct RecipeTranspiler<'a> {
     parser: CooklangParser,
-    convert_system: Option<System>,
+    options: TranspileOptions,
     output_dir: &'a Path,
+    /// The number of the next recipe when recipes are numbered
+    next_number: AtomicUsize,
+    cache: Option<BuildCache>,
 }
 
 impl<'a> RecipeTranspiler<'a> {
+    /// Creates a transpiler, the bundled units are extended with `units_file`
+    /// if given.
     pub fn new(
-        convert_system: Option<System>,
+        options: TranspileOptions,
         output_dir: &'a Path,
         units_file: Option<UnitsFile>,
-    ) -> Self {
-        let converter = if let Some(units_file) = units_file {
-            let mut builder = ConverterBuilder::new();
-            builder
-                .add_bundled_units()
-                .expect("Failed to load bundled units");
-            builder
-                .add_units_file(units_file)
-                .expect("Failed to load units file");
-            builder.finish().expect("Failed to create converter")
-        } else {
-            Converter::empty()
-        };
+    ) -> Result<Self> {
+        let converter = build_converter(units_file, options.uses_bundled_units())?;
 
-        Self {
+        let cache = (options.cache && !options.inline).then(|| BuildCache::load(output_dir));
+
+        Ok(Self {
             parser: CooklangParser::new(Extensions::all(), converter),
-            convert_system,
+            options,
             output_dir,
+            next_number: AtomicUsize::new(1),
+            cache,
+        })
+    }
+
+    /// Saves the hashes of the recipe files written so far, for the next run
+    /// to skip the unchanged ones.
+    pub fn save_cache(&self) -> Result<()> {
+        match &self.cache {
+            Some(cache) => cache.save().context("Failed to save the build cache"),
+            None => Ok(()),
+        }
+    }
+
+    /// Transpiles every recipe of a collection folder. Recipes that fail are
+    /// reported and listed in [`TranspiledCollection::failed`].
+    pub fn transpile_collection(&self, collection_path: &Path) -> Result<TranspiledCollection> {
+        let collection_name = get_collection_name(collection_path)?;
+        self.start_collection();
+        self.transpile_folder(collection_path, &collection_name)
+    }
+
+    /// Restarts the recipe numbers at 1 if recipes are numbered per
+    /// collection. Call it before transpiling the folders of a collection
+    /// with [`transpile_folder`](Self::transpile_folder).
+    pub fn start_collection(&self) {
+        if self.options.numbering == Some(Numbering::Collection) {
+            self.next_number.store(1, Ordering::SeqCst);
         }
     }
 
-    pub fn transpile_collection(&self, collection_path: &Path) -> Result<Vec<String>> {
+    /// Transpiles the recipes of a folder nested in a collection, without its
+    /// subfolders. `collection_name` is the path of the folder within the
+    /// collections, e.g. `Desserts/Cakes`.
+    pub fn transpile_folder(
+        &self,
+        collection_path: &Path,
+        collection_name: &str,
+    ) -> Result<TranspiledCollection> {
         let files = io::list_dir(collection_path)
             .with_context(|| format!("Failed to read collection: {}", collection_path.display()))?;
+        let files = order_recipe_files(collection_path, files)?;
+
+        let mut recipes = Vec::with_capacity(files.len());
+        let mut failed = Vec::new();
+        let mut skipped = 0;
+
+        // Parse every recipe first, so recipes left out by their tags don't
+        // take up a number
+        let parsed: Vec<_> = files
+            .par_iter()
+            .map(|file| (file, self.parse_file(file)))
+            .collect();
+
+        let mut selected = Vec::with_capacity(parsed.len());
+        for (file, result) in parsed {
+            match result {
+                Ok(Some(recipe)) => selected.push((file, recipe)),
+                Ok(None) => skipped += 1,
+                Err(e) => {
+                    let path = file.display();
+                    log::warn!("Failed to compile recipe {path}: {e}");
+                    failed.push(file.clone());
+                }
+            }
+        }
 
-        let collection_name = get_collection_name(collection_path)?;
-        let mut result_files = Vec::with_capacity(files.len());
+        let first_number = self.next_number.fetch_add(selected.len(), Ordering::SeqCst);
+        let results: Vec<_> = selected
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, (file, recipe))| {
+                let number = self.options.numbering.map(|_| first_number + i);
+                (
+                    file,
+                    self.transpile_recipe(file, recipe, collection_name, number),
+                )
+            })
+            .collect();
 
-        for file in files {
-            match self.transpile_recipe(&file, &collection_name) {
-                Ok(relative_path) => result_files.push(relative_path),
+        for (file, result) in results {
+            match result {
+                Ok(recipe) => recipes.push(recipe),
                 Err(e) => {
                     let path = file.display();
-                    eprintln!("Warning: Failed to compile recipe {path}: {e}");
+                    log::warn!("Failed to compile recipe {path}: {e}");
+                    failed.push(file.clone());
                 }
             }
         }
 
-        if result_files.is_empty() {
-            anyhow::bail!("No recipes were successfully compiled in collection: {collection_name}");
+        if recipes.is_empty() && failed.is_empty() && skipped == 0 {
+            anyhow::bail!("No recipes found in collection: {collection_name}");
         }
 
-        Ok(result_files)
+        Ok(TranspiledCollection {
+            recipes,
+            failed,
+            failed_collections: Vec::new(),
+        })
     }
 
-    fn transpile_recipe(&self, file: &Path, collection_name: &str) -> Result<String> {
+    /// Parses a recipe file, or returns `None` when it is filtered out by its
+    /// tags.
+    fn parse_file(&self, file: &Path) -> Result<Option<Recipe>> {
         let contents = io::read_file(file)?;
         let file_name = file
             .file_name()
@@ -82,34 +300,390 @@ impl<'a> RecipeTranspiler<'a> {
             .context("Could not convert to str")?;
 
         let recipe = self.parse_recipe(&contents, file_name)?;
+        if !self.matches_tags(&recipe.metadata) {
+            log::info!("Skipping {} because of its tags", file.display());
+            return Ok(None);
+        }
+
+        Ok(Some(recipe))
+    }
+
+    /// Transpiles a parsed recipe, `number` is prefixed to its title when
+    /// recipes are numbered.
+    fn transpile_recipe(
+        &self,
+        file: &Path,
+        recipe: Recipe,
+        collection_name: &str,
+        number: Option<usize>,
+    ) -> Result<TranspiledRecipe> {
+        let file_name = file
+            .file_name()
+            .context("Invalid file name")?
+            .to_str()
+            .context("Could not convert to str")?;
+        let file_stem = file
+            .file_stem()
+            .context("Invalid file name")?
+            .to_str()
+            .context("Could not convert to str")?;
+        let location = RecipeLocation {
+            collection: collection_name,
+            file_name,
+            stem: file_stem,
+            number,
+        };
+
         let converter = self.parser.converter();
+        let ConvertedRecipe {
+            recipe: scaled,
+            alternates,
+            conversion_errors,
+        } = scale_and_convert(recipe, &self.options, converter, file_name)?;
+
+        let mut relative_path = None;
+
+        if self.options.emit.contains(&EmitFormat::Json) {
+            let json = json::create_recipe_json(&scaled, converter, &self.options.format)?;
+            relative_path = Some(self.write_output(collection_name, file_name, "json", &json)?);
+        }
 
-        let mut scaled = recipe;
-        if let Some(system) = self.convert_system {
-            for error in scaled.convert(system, converter) {
-                eprintln!("Warning: {error}");
+        let mut content = None;
+
+        if self.options.emit.contains(&EmitFormat::Latex) {
+            let markup = match self.options.output_format {
+                OutputFormat::Latex => {
+                    let image = get_recipe_image(&scaled.metadata).and_then(|image| {
+                        copy_recipe_image(
+                            self.output_dir,
+                            collection_name,
+                            file,
+                            image,
+                            self.options.max_image_width,
+                        )
+                        .inspect_err(|e| log::warn!("Failed to copy recipe image: {e}"))
+                        .ok()
+                    });
+
+                    let latex = create_recipe(
+                        &scaled,
+                        &alternates,
+                        converter,
+                        location,
+                        image.as_deref(),
+                        &self.options.format,
+                    )?;
+                    for warning in validate_latex(&latex) {
+                        log::warn!("{}: {warning}", file.display());
+                    }
+                    latex
+                }
+                OutputFormat::Typst => render_recipe::<TypstBuilder>(
+                    &scaled,
+                    &alternates,
+                    converter,
+                    location,
+                    None,
+                    &self.options.format,
+                )?
+                .build(),
+            };
+
+            let extension = self.options.output_format.extension();
+            if self.options.inline {
+                relative_path = Some(format!("{collection_name}/{file_stem}.{extension}"));
+                content = Some(markup);
+            } else {
+                relative_path =
+                    Some(self.write_output(collection_name, file_name, extension, &markup)?);
             }
         }
 
-        let latex = create_recipe(&scaled, converter)?;
+        let path = relative_path.context("No output format selected")?;
+        log::info!("Transpiled {} to {path}", file.display());
+
+        let shopping = if self.options.shopping_list {
+            self.shopping_items(&scaled)
+        } else {
+            Vec::new()
+        };
+
+        Ok(TranspiledRecipe {
+            path,
+            title: recipe_title(&scaled, file_stem, &self.options.format).unwrap_or_default(),
+            collection: collection_name.to_string(),
+            label: location.label(),
+            references: scaled
+                .ingredients
+                .iter()
+                .filter_map(|ingredient| ingredient.reference.as_ref())
+                .map(|reference| reference_label(reference, collection_name))
+                .collect(),
+            ingredients: scaled
+                .ingredients
+                .iter()
+                .filter(|ingredient| ingredient.modifiers().should_be_listed())
+                .map(|ingredient| ingredient.name.clone())
+                .collect(),
+            content,
+            shopping,
+            conversion_errors,
+        })
+    }
+
+    /// The listed ingredients of the recipe summed over all sections, recipes
+    /// used as ingredients are not bought and left out.
+    fn shopping_items(&self, recipe: &Recipe) -> Vec<(String, GroupedQuantity)> {
+        let options = FormatOptions {
+            merge_ingredients: true,
+            ..self.options.format.clone()
+        };
+
+        get_ingredients_by_section(recipe, self.parser.converter(), &options)
+            .into_iter()
+            .flat_map(|(_, ingredients)| ingredients)
+            .filter(|grouped| grouped.ingredient.reference.is_none())
+            .map(|grouped| (grouped.ingredient.name.clone(), grouped.quantity))
+            .collect()
+    }
+
+    /// Builds the shopping list of the `recipes`, see
+    /// [`build_shopping_list`](crate::shopping::build_shopping_list).
+    pub fn shopping_list(&self, recipes: &[TranspiledRecipe]) -> LatexBuilder {
+        build_shopping_list(recipes, self.parser.converter(), &self.options.format)
+    }
+
+    /// Writes a generated recipe file like [`write_recipe`], unless the cache
+    /// shows the file is up to date.
+    fn write_output(
+        &self,
+        collection_name: &str,
+        file_name: &str,
+        extension: &str,
+        contents: &str,
+    ) -> Result<String> {
+        let Some(cache) = &self.cache else {
+            return write_recipe(
+                self.output_dir,
+                collection_name,
+                file_name,
+                extension,
+                contents,
+            );
+        };
+
+        let file_stem = Path::new(file_name)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .context("Invalid recipe file name")?;
+        let relative_path = format!("{collection_name}/{file_stem}.{extension}");
+
+        if cache.is_unchanged(self.output_dir, &relative_path, contents) {
+            log::debug!("Skipping unchanged {relative_path}");
+        } else {
+            write_recipe(
+                self.output_dir,
+                collection_name,
+                file_name,
+                extension,
+                contents,
+            )?;
+        }
+        cache.record(&relative_path, contents);
+        Ok(relative_path)
+    }
+
+    /// Whether the recipe has all the required tags and none of the excluded.
+    fn matches_tags(&self, meta: &Metadata) -> bool {
+        let tags: Vec<String> = meta
+            .tags()
+            .unwrap_or_default()
+            .iter()
+            .map(|tag| tag.trim().to_lowercase())
+            .collect();
+        let has_tag = |tag: &String| tags.contains(&tag.trim().to_lowercase());
 
-        write_recipe(self.output_dir, collection_name, file_name, &latex)
+        self.options.filter_tags.iter().all(has_tag)
+            && !self.options.exclude_tags.iter().any(has_tag)
     }
 
     fn parse_recipe(&self, contents: &str, file_name: &str) -> Result<Recipe> {
         match self.parser.parse(contents).into_result() {
             Ok((recipe, warnings)) => {
-                warnings.eprint(file_name, contents, true)?;
+                if self.options.parser_warnings && log::log_enabled!(log::Level::Warn) {
+                    warnings.eprint(file_name, contents, true)?;
+                }
                 Ok(recipe)
             }
             Err(e) => {
-                e.eprint(file_name, contents, true)?;
+                if log::log_enabled!(log::Level::Error) {
+                    e.eprint(file_name, contents, true)?;
+                }
                 Err(e.into())
             }
         }
     }
 }
 
+/// Creates the converter for the units of `units_file`. Without one the
+/// bundled units are only loaded when `bundled` is set, and quantities are
+/// otherwise never converted.
+fn build_converter(units_file: Option<UnitsFile>, bundled: bool) -> Result<Converter> {
+    if units_file.is_none() && !bundled {
+        return Ok(Converter::empty());
+    }
+
+    let mut builder = ConverterBuilder::new();
+    builder
+        .add_bundled_units()
+        .context("Failed to load bundled units")?;
+    if let Some(units_file) = units_file {
+        builder
+            .add_units_file(units_file)
+            .context("Failed to load units file")?;
+    }
+    builder.finish().context("Failed to create converter")
+}
+
+/// Transpiles the Cooklang `contents` to the LaTeX of a single recipe without
+/// touching the filesystem. No image is copied, so `\recipeimage` is left out.
+/// Like [`RecipeTranspiler::new`], the bundled units are extended with
+/// `units_file` if given.
+pub fn transpile_recipe_str(
+    contents: &str,
+    options: &TranspileOptions,
+    units_file: Option<UnitsFile>,
+) -> Result<String> {
+    let parser = CooklangParser::new(
+        Extensions::all(),
+        build_converter(units_file, options.uses_bundled_units())?,
+    );
+    let (recipe, _warnings) = parser.parse(contents).into_result()?;
+    let converter = parser.converter();
+
+    let location = RecipeLocation {
+        collection: "",
+        file_name: "recipe.cook",
+        stem: "recipe",
+        number: None,
+    };
+    let ConvertedRecipe {
+        recipe, alternates, ..
+    } = scale_and_convert(recipe, options, converter, location.file_name)?;
+
+    create_recipe(
+        &recipe,
+        &alternates,
+        converter,
+        location,
+        None,
+        &options.format,
+    )
+}
+
+/// A recipe scaled and converted to the requested unit systems.
+struct ConvertedRecipe {
+    /// The recipe in the first system
+    recipe: Recipe,
+    /// A copy of the recipe for every other system
+    alternates: Vec<Recipe>,
+    /// The number of quantities that could not be converted
+    conversion_errors: usize,
+}
+
+/// Scales the recipe and converts it to the requested unit systems. Fails if
+/// a quantity could not be converted and `convert_strict` is set.
+fn scale_and_convert(
+    mut recipe: Recipe,
+    options: &TranspileOptions,
+    converter: &Converter,
+    file_name: &str,
+) -> Result<ConvertedRecipe> {
+    // Scale before converting so the converted units fit the scaled amounts.
+    // Ingredients, cookware and inline quantities in steps are scaled, timers
+    // are kept as cooking times rarely depend on the amount
+    if let Some(servings) = options.servings {
+        let factor = get_u64_meta(&recipe.metadata, StdKey::Servings)
+            .map(|base| servings as f64 / base as f64);
+        match recipe.scale_to_servings(servings, converter) {
+            Ok(_) if options.scale_timers => match factor {
+                Some(factor) => scale_timers(&mut recipe, factor),
+                None => log::warn!("Not scaling the timers of {file_name}, its servings vary"),
+            },
+            Ok(_) => {}
+            Err(e) => log::warn!("Not scaling {file_name} to {servings} servings: {e}"),
+        }
+    } else if options.scale != 1.0 {
+        recipe.scale(options.scale, converter);
+        if options.scale_timers {
+            scale_timers(&mut recipe, options.scale);
+        }
+    }
+
+    // Every system after the first is shown next to the main quantities
+    let systems = convert_systems(&recipe.metadata, options, file_name);
+    let mut alternates = Vec::new();
+    let mut conversion_errors = 0;
+    if let Some((system, others)) = systems.split_first() {
+        for other in others {
+            let mut alternate = recipe.clone();
+            conversion_errors += convert_recipe(&mut alternate, *other, converter);
+            alternates.push(alternate);
+        }
+
+        conversion_errors += convert_recipe(&mut recipe, *system, converter);
+    }
+
+    if options.convert_strict && conversion_errors > 0 {
+        anyhow::bail!("{conversion_errors} quantities in {file_name} could not be converted");
+    }
+
+    Ok(ConvertedRecipe {
+        recipe,
+        alternates,
+        conversion_errors,
+    })
+}
+
+/// The systems to convert the recipe to. `convert: none` or e.g.
+/// `convert: imperial` in the metadata overrides the `convert_systems` option,
+/// e.g. for a traditional recipe that should keep its units.
+fn convert_systems(meta: &Metadata, options: &TranspileOptions, file_name: &str) -> Vec<System> {
+    let Some(system) = meta.get("convert").and_then(|convert| convert.as_str()) else {
+        return options.convert_systems.clone();
+    };
+
+    match system.trim().to_lowercase().as_str() {
+        "none" => Vec::new(),
+        system => match system.parse() {
+            Ok(system) => vec![system],
+            Err(_) => {
+                log::warn!("Ignoring unknown system {system} in {file_name}");
+                options.convert_systems.clone()
+            }
+        },
+    }
+}
+
+/// Multiplies the duration of every timer by `factor`.
+fn scale_timers(recipe: &mut Recipe, factor: f64) {
+    let scale = |number: &Number| Number::from(number.value() * factor);
+
+    for timer in &mut recipe.timers {
+        if let Some(quantity) = &timer.quantity {
+            let value = match quantity.value() {
+                Value::Number(number) => Value::Number(scale(number)),
+                Value::Range { start, end } => Value::Range {
+                    start: scale(start),
+                    end: scale(end),
+                },
+                Value::Text(_) => continue,
+            };
+            timer.quantity = Some(Quantity::new(value, quantity.unit().map(String::from)));
+        }
+    }
+}
+
 fn get_u64_meta(meta: &Metadata, key: StdKey) -> Option<u64> {
     meta.get(key).and_then(|x| x.as_u64())
 }
@@ -123,144 +697,629 @@ struct RecipeTime {
 impl RecipeTime {
     fn from_metadata(metadata: &Metadata) -> Self {
         Self {
-            prep_time: get_u64_meta(metadata, StdKey::PrepTime),
-            cook_time: get_u64_meta(metadata, StdKey::CookTime),
+            prep_time: Self::minutes_meta(metadata, StdKey::PrepTime, "prep"),
+            cook_time: Self::minutes_meta(metadata, StdKey::CookTime, "cook"),
         }
     }
 
-    fn format_time(minutes: u64) -> String {
-        if minutes < 60 {
-            format!("{minutes} mins")
-        } else {
-            let hours = minutes / 60;
-            let mins = minutes % 60;
-            if mins == 0 {
-                format!("{hours} hrs")
-            } else {
-                format!("{hours} hrs {mins} mins")
+    /// Reads a time in minutes from the standard key, or the common spellings
+    /// `prep_time`, `prep time`, `preptime` and `time: { prep: ... }`. The
+    /// value may be a number of minutes or a duration like `1h 30m`.
+    fn minutes_meta(metadata: &Metadata, key: StdKey, name: &str) -> Option<u64> {
+        let value = metadata
+            .get(key)
+            .or_else(|| {
+                [
+                    format!("{name}_time"),
+                    format!("{name} time"),
+                    format!("{name}time"),
+                ]
+                .iter()
+                .find_map(|alias| metadata.get(alias.as_str()))
+            })
+            .or_else(|| metadata.get("time")?.get(name))?;
+
+        value
+            .as_u64()
+            .or_else(|| value.as_f64().map(|minutes| minutes.round() as u64))
+            .or_else(|| value.as_str().and_then(Self::parse_minutes))
+    }
+
+    /// Parses a duration like `90`, `90 min`, `1h30m`, `1 hour 30 minutes` or
+    /// `1:30` into minutes, a number without a unit counts as minutes.
+    fn parse_minutes(text: &str) -> Option<u64> {
+        let text = text.trim().to_lowercase();
+
+        if let Some((hours, minutes)) = text.split_once(':') {
+            let hours: u64 = hours.trim().parse().ok()?;
+            let minutes: u64 = minutes.trim().parse().ok()?;
+            return Some(hours * 60 + minutes);
+        }
+
+        let mut total = 0.0;
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            let number_end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let number: f64 = rest[..number_end].parse().ok()?;
+            rest = rest[number_end..].trim_start();
+
+            let unit_end = rest
+                .find(|c: char| !c.is_alphabetic())
+                .unwrap_or(rest.len());
+            let minutes_per_unit = match &rest[..unit_end] {
+                "" | "m" | "min" | "mins" | "minute" | "minutes" => 1.0,
+                "h" | "hr" | "hrs" | "hour" | "hours" => 60.0,
+                "d" | "day" | "days" => 24.0 * 60.0,
+                "s" | "sec" | "secs" | "second" | "seconds" => 1.0 / 60.0,
+                _ => return None,
+            };
+            total += number * minutes_per_unit;
+            rest = rest[unit_end..].trim_start_matches([' ', ',']);
+        }
+
+        (total > 0.0).then(|| total.round() as u64)
+    }
+
+    /// The prep and cook time together, or whichever of them is known.
+    fn total_minutes(&self) -> Option<u64> {
+        match (self.prep_time, self.cook_time) {
+            (None, None) => None,
+            (prep, cook) => Some(prep.unwrap_or(0) + cook.unwrap_or(0)),
+        }
+    }
+
+    fn format_minutes<R: RecipeRenderer>(minutes: u64, labels: &TimeLabels) -> String {
+        Self::format_time::<R>(minutes * 60, labels)
+    }
+
+    /// Formats a duration as e.g. `45 secs` or `2 days 3 hrs`, leaving out
+    /// the units that are zero.
+    fn format_time<R: RecipeRenderer>(seconds: u64, labels: &TimeLabels) -> String {
+        let units = [
+            (24 * 60 * 60, &labels.day, &labels.days),
+            (60 * 60, &labels.hour, &labels.hours),
+            (60, &labels.minute, &labels.minutes),
+            (1, &labels.second, &labels.seconds),
+        ];
+
+        if seconds == 0 {
+            return format!("0 {}", R::escape(&labels.minutes));
+        }
+
+        let mut remaining = seconds;
+        let mut parts = Vec::new();
+        for (size, singular, plural) in units {
+            let count = remaining / size;
+            remaining %= size;
+            if count > 0 {
+                let label = if count == 1 { singular } else { plural };
+                parts.push(format!("{count} {}", R::escape(label)));
             }
         }
+
+        parts.join(" ")
+    }
+
+    /// The length of a timer quantity in seconds, if it uses a time unit.
+    fn quantity_seconds(qty: &Quantity) -> Option<u64> {
+        let seconds_per_unit = match qty.unit()?.trim().to_lowercase().as_str() {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 3600.0,
+            "d" | "day" | "days" => 86400.0,
+            _ => return None,
+        };
+
+        match qty.value() {
+            Value::Number(number) => Some((number.value() * seconds_per_unit).round() as u64),
+            _ => None,
+        }
     }
 }
 
-pub fn create_recipe(recipe: &Recipe, converter: &Converter) -> Result<String> {
-    let description = recipe
-        .metadata
-        .description()
-        .context("Recipe must have a description")?;
+/// Where a recipe lives, used to label it and to resolve references to other
+/// recipes.
+#[derive(Debug, Clone, Copy)]
+pub struct RecipeLocation<'a> {
+    pub collection: &'a str,
+    pub file_name: &'a str,
+    pub stem: &'a str,
+    /// The number prefixed to the title when recipes are numbered
+    pub number: Option<usize>,
+}
+
+impl RecipeLocation<'_> {
+    pub fn label(&self) -> String {
+        recipe_label(self.collection, self.stem)
+    }
+}
+
+/// The LaTeX label of the recipe `stem` in `collection`, e.g.
+/// `recipe:Desserts:apple-pie`. Characters other than letters, digits and `-`
+/// are replaced by `-`, so the label is safe to use with `\ref` and `\pageref`.
+pub fn recipe_label(collection: &str, stem: &str) -> String {
+    let clean = |part: &str| {
+        part.chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect::<String>()
+    };
 
-    let mut latex = LatexBuilder::new();
-    let recipe_content = build_recipe_content(recipe, converter);
+    format!("recipe:{}:{}", clean(collection), clean(stem))
+}
+
+/// Resolves a recipe reference to a label. References without a folder point
+/// to a recipe in the same collection, references starting with `..` climb
+/// up from the referencing collection, and other folders start at the
+/// collections root, so `@./Desserts/Cakes/sponge{}` links to
+/// `recipe:Desserts-Cakes:sponge`.
+fn reference_label(reference: &RecipeReference, collection: &str) -> String {
+    let mut folders: Vec<&str> = match reference.components.first().map(String::as_str) {
+        Some("..") => collection.split('/').collect(),
+        _ => Vec::new(),
+    };
+    for component in &reference.components {
+        match component.as_str() {
+            "" | "." => {}
+            ".." => {
+                folders.pop();
+            }
+            folder => folders.push(folder),
+        }
+    }
 
-    let meta = recipe_meta(&recipe.metadata);
+    if folders.is_empty() {
+        return recipe_label(collection, &reference.name);
+    }
+    recipe_label(&folders.join("/"), &reference.name)
+}
 
-    Ok(latex
-        .add_builder(&build_recipe_header(recipe))
-        .add_simple_command("recipedesc", description)
-        .add_command("recipemeta", &meta)
-        .add_env("recipe", &recipe_content)
-        .build())
+/// Converts the recipe, returning the number of quantities that could not be
+/// converted.
+fn convert_recipe(recipe: &mut Recipe, system: System, converter: &Converter) -> usize {
+    let errors = recipe.convert(system, converter);
+    for error in &errors {
+        log::warn!("{error}");
+    }
+    errors.len()
 }
 
-fn build_recipe_header(recipe: &Recipe) -> LatexBuilder {
-    let title = recipe
-        .metadata
-        .title()
-        .context("Recipe must have a title")
-        .unwrap();
+/// Creates the LaTeX for `recipe`, see [`render_recipe`].
+pub fn create_recipe(
+    recipe: &Recipe,
+    alternates: &[Recipe],
+    converter: &Converter,
+    location: RecipeLocation,
+    image: Option<&str>,
+    options: &FormatOptions,
+) -> Result<String> {
+    render_recipe::<LatexBuilder>(recipe, alternates, converter, location, image, options)
+        .map(|latex| latex.build())
+}
+
+/// Renders `recipe` in the markup of `R`, shared by every output format. The
+/// quantities of the `alternates`, the same recipe converted to other unit
+/// systems, are shown in parentheses.
+pub fn render_recipe<R: RecipeRenderer>(
+    recipe: &Recipe,
+    alternates: &[Recipe],
+    converter: &Converter,
+    location: RecipeLocation,
+    image: Option<&str>,
+    options: &FormatOptions,
+) -> Result<R> {
+    let mut output = R::default();
+    let recipe_content =
+        build_recipe_content::<R>(recipe, alternates, converter, location, options);
+
+    // `hide_meta: true` leaves out the servings and times, e.g. for sauces
+    let meta = if hides_meta(&recipe.metadata) {
+        None
+    } else {
+        Some(recipe_meta::<R>(&recipe.metadata, options)?)
+    };
+
+    output.add_comment(&format!(
+        "Generated from {}/{}",
+        location.collection, location.file_name
+    ));
+
+    if let Some(image) = image {
+        output.add_simple_command("recipeimage", image);
+    }
+
+    output
+        .add_command(
+            "recipeheader",
+            &recipe_header::<R>(recipe, location, options)?,
+        )
+        .add_label(&location.label());
+
+    if let Some(tags) = recipe_tags::<R>(&recipe.metadata) {
+        output.add_simple_command("recipetags", &tags);
+    }
+
+    if let Some(description) = recipe.metadata.description() {
+        output.add_simple_command("recipedesc", &render_inline::<R>(description));
+    }
+
+    if let Some(source) = recipe_source::<R>(&recipe.metadata) {
+        output.add_command("recipesource", &source);
+    }
+
+    if let Some(url) = recipe_source_url(&recipe.metadata) {
+        output.add_simple_command("recipesourceurl", &R::escape_url(&url));
+    }
+
+    if let Some(meta) = meta {
+        output.add_command("recipemeta", &meta);
+
+        if let Some(total) = RecipeTime::from_metadata(&recipe.metadata).total_minutes() {
+            output.add_simple_command(
+                "recipetotaltime",
+                &RecipeTime::format_minutes::<R>(total, &options.time_labels),
+            );
+        }
+    }
 
-    let mut args = vec![Arg::required(&sanitize_latex(title))];
+    if let Some(recipe_yield) = recipe_yield::<R>(&recipe.metadata) {
+        output.add_simple_command("recipeyield", &recipe_yield);
+    }
+
+    for (key, value) in recipe_extra_meta::<R>(&recipe.metadata, &options.extra_meta_keys) {
+        output.add_command(
+            "recipemetaextra",
+            &[Arg::required(&key), Arg::required(&value)],
+        );
+    }
+
+    if let Some(nutrition) = recipe_nutrition::<R>(&recipe.metadata) {
+        output.add_command("recipenutrition", &nutrition);
+    }
+
+    let environment = recipe_environment(&recipe.metadata, options);
+    output.add_env(environment, &recipe_content);
+    Ok(output)
+}
+
+/// The environment the recipe is wrapped in, selected by its `layout`.
+/// Unknown layouts fall back to the default `recipe` environment.
+fn recipe_environment<'a>(meta: &Metadata, options: &'a FormatOptions) -> &'a str {
+    let Some(layout) = meta.get("layout").and_then(|layout| layout.as_str()) else {
+        return "recipe";
+    };
+
+    match options.layouts.get(layout.trim()) {
+        Some(environment) => environment,
+        None => {
+            log::warn!("Unknown layout {layout}, using the default layout");
+            "recipe"
+        }
+    }
+}
+
+/// The arguments of `\recipeheader`, the numbered title and the name of the
+/// source if there is one.
+fn recipe_header<R: RecipeRenderer>(
+    recipe: &Recipe,
+    location: RecipeLocation,
+    options: &FormatOptions,
+) -> Result<Vec<Arg>> {
+    let title = recipe_title(recipe, location.stem, options)?;
+
+    let mut args = vec![Arg::required(&numbered_title(
+        &R::escape(&title),
+        location.number,
+    ))];
 
     if let Some(Some(source)) = recipe
         .metadata
         .source()
         .map(|s| s.name().map(|n| n.to_string()))
     {
-        args.push(Arg::optional(&sanitize_latex(&source)))
+        args.push(Arg::optional(&R::escape(&source)))
+    }
+
+    Ok(args)
+}
+
+/// The title of the recipe. Without a title in the metadata the recipe fails,
+/// unless `title_from_filename` names it after its file, e.g. `banana-bread`
+/// becomes `Banana Bread`.
+pub(crate) fn recipe_title(recipe: &Recipe, stem: &str, options: &FormatOptions) -> Result<String> {
+    if let Some(title) = recipe.metadata.title() {
+        return Ok(title.to_string());
+    }
+    if !options.title_from_filename {
+        anyhow::bail!("Recipe must have a title");
     }
 
-    let mut latex = LatexBuilder::new();
-    latex.add_command("recipeheader", &args);
-    latex
+    let title = stem
+        .split(['-', '_', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(title)
+}
+
+/// Prefixes the recipe number to the already escaped `title`, e.g. `2. Waffles`.
+pub(crate) fn numbered_title(title: &str, number: Option<usize>) -> String {
+    match number {
+        Some(number) => format!("{number}. {title}"),
+        None => title.to_string(),
+    }
 }
 
-fn build_recipe_content(recipe: &Recipe, converter: &Converter) -> LatexBuilder {
-    let mut content = LatexBuilder::new();
+fn recipe_source<R: RecipeRenderer>(meta: &Metadata) -> Option<Vec<Arg>> {
+    let author = meta
+        .author()
+        .and_then(|author| author.name().map(String::from));
+    let source = meta
+        .source()
+        .and_then(|source| source.name().map(String::from));
+
+    if author.is_none() && source.is_none() {
+        return None;
+    }
 
-    let grouped_ingredients = get_ingredients_by_section(recipe, converter);
-    let ingredients = ingredient_list(&grouped_ingredients);
-    let instructions = instruction_list(recipe);
+    Some(vec![
+        Arg::required(&R::escape(author.as_deref().unwrap_or_default())),
+        Arg::required(&R::escape(source.as_deref().unwrap_or_default())),
+    ])
+}
 
-    content
-        .add_env("ingredients", &ingredients)
-        .add_env("instructions", &instructions);
+/// The URL of the source, e.g. `source: https://example.com/recipe`.
+fn recipe_source_url(meta: &Metadata) -> Option<String> {
+    let url = meta.source()?.url()?.trim();
+    (url.starts_with("http://") || url.starts_with("https://")).then(|| url.to_string())
+}
+
+fn recipe_tags<R: RecipeRenderer>(meta: &Metadata) -> Option<String> {
+    let tags: Vec<String> = meta
+        .tags()?
+        .iter()
+        .map(|tag| R::escape(tag.trim()))
+        .filter(|tag| !tag.is_empty())
+        .collect();
+
+    if tags.is_empty() {
+        None
+    } else {
+        Some(tags.join(", "))
+    }
+}
+
+fn build_recipe_content<R: RecipeRenderer>(
+    recipe: &Recipe,
+    alternates: &[Recipe],
+    converter: &Converter,
+    location: RecipeLocation,
+    options: &FormatOptions,
+) -> R {
+    let mut content = R::default();
+
+    let grouped_ingredients = get_ingredients_by_section(recipe, converter, options);
+    let alternate_ingredients: Vec<_> = alternates
+        .iter()
+        .map(|alternate| get_ingredients_by_section(alternate, converter, options))
+        .collect();
+    let ingredients = ingredient_list::<R>(&grouped_ingredients, &alternate_ingredients, options);
+    let cookware = cookware_list::<R>(recipe, converter, options);
+    let instructions = instruction_list::<R>(recipe, location, options);
+
+    // Empty lists are left out, e.g. a recipe with only metadata and
+    // ingredients has no instructions
+    if !ingredients.is_empty() {
+        // `ingredient_columns` in the metadata overrides the option, 1 turns
+        // the columns off for a single recipe
+        let columns = recipe
+            .metadata
+            .get("ingredient_columns")
+            .and_then(|columns| columns.as_u64())
+            .map(|columns| columns as usize)
+            .or(options.ingredient_columns)
+            .filter(|columns| *columns > 1);
+
+        match columns {
+            Some(columns) => {
+                let mut multicols = R::default();
+                multicols.add_columns(columns, &ingredients);
+                content.add_env("ingredients", &multicols)
+            }
+            None => content.add_env("ingredients", &ingredients),
+        };
+    }
+
+    if !cookware.is_empty() {
+        content.add_env("cookware", &cookware);
+    }
+
+    if !instructions.is_empty() {
+        content.add_env("instructions", &instructions);
+    }
 
     let note = get_recipe_note(&recipe.metadata);
     if let Some(note) = note {
-        content.add_simple_command("recipenote", &sanitize_latex(&note));
+        content.add_simple_command("recipenote", &render_inline::<R>(&note));
     }
 
     content
 }
 
-fn recipe_meta(meta: &Metadata) -> Vec<Arg> {
-    let servings = meta
-        .servings()
-        .map(|s| s.to_string())
-        .expect("Servings must be defined");
+fn recipe_meta<R: RecipeRenderer>(meta: &Metadata, options: &FormatOptions) -> Result<Vec<Arg>> {
+    let servings = match recipe_servings::<R>(meta) {
+        Some(servings) => servings,
+        None if options.require_servings => anyhow::bail!("Recipe must define servings"),
+        None => String::new(),
+    };
 
     let times = RecipeTime::from_metadata(meta);
     let prep_time = times
         .prep_time
-        .map(RecipeTime::format_time)
+        .map(|minutes| RecipeTime::format_minutes::<R>(minutes, &options.time_labels))
         .unwrap_or_default();
     let cook_time = times
         .cook_time
-        .map(RecipeTime::format_time)
+        .map(|minutes| RecipeTime::format_minutes::<R>(minutes, &options.time_labels))
         .unwrap_or_default();
 
-    vec![
+    Ok(vec![
         Arg::required(&servings),
         Arg::required(&prep_time),
         Arg::required(&cook_time),
-        Arg::required("Moderate"),
-    ]
+        Arg::required(&recipe_difficulty::<R>(meta)),
+    ])
 }
 
-fn format_quantity(qty: &Quantity) -> String {
-    match qty.unit() {
-        Some(unit) => {
-            let value = qty.value();
-            format!("{value} {unit}")
-        }
-        None => {
-            let value = qty.value();
-            format!("{value}")
-        }
-    }
+fn hides_meta(meta: &Metadata) -> bool {
+    meta.get("hide_meta")
+        .and_then(|hide| hide.as_bool())
+        .unwrap_or(false)
 }
 
-fn get_ingredients_by_section<'a>(
-    recipe: &'a Recipe,
-    converter: &'a Converter,
-) -> Vec<(Option<String>, Vec<GroupedIngredient<'a>>)> {
-    let mut sections: Vec<(Option<String>, Vec<GroupedIngredient>)> = Vec::new();
+/// Formats the declared servings, a range like `4-6` is shown with an en dash
+/// and multiple values like `2|4` as `2, 4`.
+fn recipe_servings<R: RecipeRenderer>(meta: &Metadata) -> Option<String> {
+    let servings = meta.get(StdKey::Servings)?;
+    if let Some(number) = servings.as_u64() {
+        return Some(number.to_string());
+    }
 
-    let mut listed_ingredients = HashSet::new();
+    let values: Vec<String> = servings
+        .as_str()?
+        .split('|')
+        .map(|value| {
+            value
+                .split('-')
+                .map(|part| R::escape(part.trim()))
+                .collect::<Vec<_>>()
+                .join("--")
+        })
+        .filter(|value| !value.is_empty())
+        .collect();
 
-    for section in &recipe.sections {
-        let mut ingredients: HashMap<String, (&usize, &'a Ingredient, GroupedQuantity)> =
-            HashMap::new();
+    (!values.is_empty()).then(|| values.join(", "))
+}
 
-        for content in &section.content {
-            if let Content::Step(step) = content {
-                for item in &step.items {
-                    if let Item::Ingredient { index } = item {
-                        let ingredient = &recipe.ingredients[*index];
-                        let name = ingredient.name.clone();
+/// What the recipe makes, e.g. `12 muffins`, independent of the servings.
+fn recipe_yield<R: RecipeRenderer>(meta: &Metadata) -> Option<String> {
+    let value = meta.get("yield")?;
+    let text = match value.as_f64() {
+        Some(number) => format_number(number),
+        None => value.as_str()?.trim().to_string(),
+    };
 
-                        if ingredient.modifiers().should_be_listed() {
-                            if !listed_ingredients.contains(&name) {
+    (!text.is_empty()).then(|| R::escape(&text))
+}
+
+/// The sanitized values of the custom metadata `keys`, in the given order.
+/// Keys the recipe doesn't set, or sets to a list or map, are left out.
+fn recipe_extra_meta<R: RecipeRenderer>(meta: &Metadata, keys: &[String]) -> Vec<(String, String)> {
+    keys.iter()
+        .filter_map(|key| {
+            let value = meta.get(key.as_str())?;
+            let value = match value.as_f64() {
+                Some(number) => format_number(number),
+                None => value
+                    .as_str()
+                    .map(|text| text.trim().to_string())
+                    .or_else(|| value.as_bool().map(|flag| flag.to_string()))?,
+            };
+            Some((R::escape(key), R::escape(&value)))
+        })
+        .collect()
+}
+
+fn recipe_difficulty<R: RecipeRenderer>(meta: &Metadata) -> String {
+    let difficulty = meta
+        .get("difficulty")
+        .and_then(|d| d.as_str())
+        .map(str::trim)
+        .unwrap_or_default();
+
+    let normalized = match difficulty.to_lowercase().as_str() {
+        "" => "Moderate",
+        "easy" => "Easy",
+        "moderate" => "Moderate",
+        "hard" => "Hard",
+        _ => difficulty,
+    };
+
+    R::escape(normalized)
+}
+
+const NUTRITION_KEYS: [&str; 4] = ["calories", "protein", "fat", "carbohydrates"];
+
+fn recipe_nutrition<R: RecipeRenderer>(meta: &Metadata) -> Option<Vec<Arg>> {
+    let values: Vec<Option<String>> = NUTRITION_KEYS
+        .iter()
+        .map(|key| {
+            meta.get(*key).and_then(|value| match value.as_f64() {
+                Some(number) => Some(format_number(number)),
+                None => value.as_str().map(String::from),
+            })
+        })
+        .collect();
+
+    if values.iter().all(Option::is_none) {
+        return None;
+    }
+
+    Some(
+        values
+            .iter()
+            .map(|value| Arg::required(&R::escape(value.as_deref().unwrap_or_default())))
+            .collect(),
+    )
+}
+
+/// Groups the listed ingredients of every section. With `merge_ingredients`
+/// the ingredients of all sections are summed into a single unnamed section.
+///
+/// Ingredients are listed in the order they are first mentioned, an ingredient
+/// that is used again later keeps the position of its first mention, unless
+/// `ingredient_sort` sorts them alphabetically. References
+/// to an earlier ingredient (`&flour{50%g}`) are not listed on their own, their
+/// quantity is added to the referenced ingredient, e.g. `@flour{200%g}` and
+/// `&flour{50%g}` give a single `250 g flour`.
+pub(crate) fn get_ingredients_by_section<'a>(
+    recipe: &'a Recipe,
+    converter: &'a Converter,
+    options: &FormatOptions,
+) -> IngredientSections<'a> {
+    let mut sections: Vec<(Option<String>, Vec<GroupedIngredient>)> = Vec::new();
+
+    let mut listed_ingredients = HashSet::new();
+    let mut ingredients: IndexMap<String, (&usize, &'a Ingredient, GroupedQuantity)> =
+        IndexMap::new();
+
+    for section in &recipe.sections {
+        for content in &section.content {
+            if let Content::Step(step) = content {
+                for item in &step.items {
+                    if let Item::Ingredient { index } = item {
+                        let ingredient = &recipe.ingredients[*index];
+                        // Group case-insensitively, the first occurrence decides the
+                        // displayed casing
+                        let name = ingredient.name.trim().to_lowercase();
+
+                        if ingredient.modifiers().should_be_listed() {
+                            if !listed_ingredients.contains(&name) {
                                 listed_ingredients.insert(name.clone());
                             }
                         } else if !listed_ingredients.contains(&name)
@@ -285,110 +1344,355 @@ fn get_ingredients_by_section<'a>(
             }
         }
 
-        let section_name = section.name.clone();
-        let mut output_ingredients = ingredients
-            .iter()
-            .map(|(_name, (index, ingredient, quantity))| GroupedIngredient {
-                index: **index,
-                ingredient,
-                quantity: quantity.clone(),
-            })
-            .collect::<Vec<_>>();
-        output_ingredients.sort_by_key(|gi| gi.index);
-        sections.push((section_name.clone(), output_ingredients));
+        if !options.merge_ingredients {
+            sections.push((section.name.clone(), grouped_ingredients(&ingredients)));
+            ingredients.clear();
+        }
+    }
+
+    if options.merge_ingredients {
+        sections.push((None, grouped_ingredients(&ingredients)));
+    }
+
+    if options.ingredient_sort == IngredientSort::Alpha {
+        for (_, grouped) in &mut sections {
+            // Stable, so ingredients differing only in case keep their order
+            grouped.sort_by_cached_key(|grouped| grouped.ingredient.name.to_lowercase());
+        }
+    }
+
+    // Compatible units are already summed in the unit of the first mention,
+    // e.g. `100 g` and `0.1 kg` give `200 g`, the total is shown in the most
+    // fitting unit of its system
+    if options.normalize_units {
+        for grouped in sections.iter_mut().flat_map(|(_, grouped)| grouped) {
+            if let Err(e) = grouped.quantity.fit(converter) {
+                log::debug!("Not normalizing {}: {e}", grouped.ingredient.name);
+            }
+        }
     }
 
     sections
 }
 
-fn ingredient_list(ingredients: &Vec<(Option<String>, Vec<GroupedIngredient>)>) -> LatexBuilder {
-    let mut latex = LatexBuilder::new();
+fn grouped_ingredients<'a>(
+    ingredients: &IndexMap<String, (&usize, &'a Ingredient, GroupedQuantity)>,
+) -> Vec<GroupedIngredient<'a>> {
+    ingredients
+        .values()
+        .map(|(index, ingredient, quantity)| GroupedIngredient {
+            index: **index,
+            ingredient,
+            quantity: quantity.clone(),
+        })
+        .collect()
+}
+
+pub(crate) type IngredientSections<'a> = Vec<(Option<String>, Vec<GroupedIngredient<'a>>)>;
+
+pub(crate) fn format_grouped_quantity<R: RecipeRenderer>(
+    quantity: &GroupedQuantity,
+    options: &FormatOptions,
+) -> Option<String> {
+    quantity
+        .iter()
+        .map(|q| format_quantity::<R>(q, options))
+        .reduce(|a, b| format!("{a}{}{b}", options.list_separator()))
+}
+
+/// The quantity of an ingredient of the section `section_index`, followed by
+/// its differing quantities in the `alternates` in parentheses, e.g.
+/// `240 ml (1 cup)`.
+pub(crate) fn ingredient_quantity<R: RecipeRenderer>(
+    grouped: &GroupedIngredient,
+    section_index: usize,
+    alternates: &[IngredientSections],
+    options: &FormatOptions,
+) -> Option<String> {
+    let quantity = format_grouped_quantity::<R>(&grouped.quantity, options)?;
+
+    let alternate_quantities: Vec<String> = alternates
+        .iter()
+        .filter_map(|alternate| {
+            alternate
+                .get(section_index)?
+                .1
+                .iter()
+                .find(|other| other.index == grouped.index)
+        })
+        .filter_map(|other| format_grouped_quantity::<R>(&other.quantity, options))
+        .filter(|alternate| *alternate != quantity)
+        .collect();
+
+    if alternate_quantities.is_empty() {
+        Some(quantity)
+    } else {
+        Some(format!("{quantity} ({})", alternate_quantities.join("; ")))
+    }
+}
+
+/// The name of an ingredient in the ingredient list, followed by its
+/// preparation note, e.g. `onion, finely diced`.
+pub(crate) fn ingredient_name<R: RecipeRenderer>(ingredient: &Ingredient) -> String {
+    let name = R::escape(&ingredient.name);
+    match &ingredient.note {
+        Some(note) => format!("{name}, {}", R::escape(note)),
+        None => name,
+    }
+}
+
+fn ingredient_list<R: RecipeRenderer>(
+    ingredients: &IngredientSections,
+    alternates: &[IngredientSections],
+    options: &FormatOptions,
+) -> R {
+    let mut output = R::default();
 
-    for (section_name, ingredients) in ingredients {
+    for (section_index, (section_name, ingredients)) in ingredients.iter().enumerate() {
         if ingredients.is_empty() {
             continue;
         }
         if let Some(name) = section_name {
-            latex.add_simple_command("ingredientsection", &sanitize_latex(name));
+            output.add_simple_command("ingredientsection", &R::escape(name));
         }
 
-        for GroupedIngredient {
-            ingredient,
-            quantity,
-            ..
-        } in ingredients
-        {
-            let mut parts = Vec::new();
+        for grouped in ingredients {
+            let quantity = ingredient_quantity::<R>(grouped, section_index, alternates, options);
+            let name = ingredient_name::<R>(grouped.ingredient);
 
-            if let Some(qty_str) = quantity
-                .iter()
-                .map(format_quantity)
-                .reduce(|a, b| format!("{a}, {b}"))
+            // Separate arguments let the template align the quantities
+            let mut args = if options.split_ingredient_args {
+                vec![
+                    Arg::required(quantity.as_deref().unwrap_or_default()),
+                    Arg::required(&name),
+                ]
+            } else {
+                let line = match quantity {
+                    Some(quantity) => format!("{quantity} {name}"),
+                    None => name,
+                };
+                vec![Arg::required(&line)]
+            };
+
+            if let Some(flag) =
+                R::OPTIONAL_FLAG.filter(|_| grouped.ingredient.modifiers().is_optional())
             {
-                parts.push(qty_str);
+                args.push(Arg::optional(flag));
             }
 
-            parts.push(ingredient.name.clone());
+            output.add_command("ingredient", &args);
+        }
+    }
+
+    output
+}
+
+/// Groups the listed cookware by name, summing the quantities. Hidden
+/// cookware is left out, the first mention of an item provides its note and
+/// modifiers.
+pub(crate) fn group_cookware<'a>(
+    recipe: &'a Recipe,
+    converter: &Converter,
+) -> Vec<(&'a Cookware, GroupedQuantity)> {
+    let mut grouped: Vec<(&Cookware, GroupedQuantity)> = Vec::new();
 
-            let mut args = vec![Arg::required(&sanitize_latex(&parts.join(" ")))];
+    for cookware in &recipe.cookware {
+        if !cookware.modifiers().should_be_listed() {
+            continue;
+        }
 
-            if ingredient.modifiers().is_optional() {
-                args.push(Arg::optional("\\BooleanTrue"));
+        let index = match grouped
+            .iter()
+            .position(|(first, _)| first.name == cookware.name)
+        {
+            Some(index) => index,
+            None => {
+                grouped.push((cookware, GroupedQuantity::default()));
+                grouped.len() - 1
             }
+        };
 
-            latex.add_command("ingredient", &args);
+        if let Some(q) = &cookware.quantity {
+            grouped[index].1.add(q, converter);
         }
     }
 
-    latex
+    grouped
 }
 
-fn instruction_list(recipe: &Recipe) -> LatexBuilder {
-    let mut latex = LatexBuilder::new();
+fn cookware_list<R: RecipeRenderer>(
+    recipe: &Recipe,
+    converter: &Converter,
+    options: &FormatOptions,
+) -> R {
+    let mut output = R::default();
+
+    for (cookware, quantity) in group_cookware(recipe, converter) {
+        let mut parts = Vec::new();
+
+        if let Some(qty_str) = format_grouped_quantity::<R>(&quantity, options) {
+            parts.push(qty_str);
+        }
+
+        let mut name = R::escape(&cookware.name);
+        if let Some(note) = &cookware.note {
+            name = format!("{name}, {}", R::escape(note));
+        }
+        parts.push(name);
+
+        let mut args = vec![Arg::required(&parts.join(" "))];
+        if let Some(flag) = R::OPTIONAL_FLAG.filter(|_| cookware.modifiers().is_optional()) {
+            args.push(Arg::optional(flag));
+        }
+
+        output.add_command("cookware", &args);
+    }
+
+    output
+}
+
+fn instruction_list<R: RecipeRenderer>(
+    recipe: &Recipe,
+    location: RecipeLocation,
+    options: &FormatOptions,
+) -> R {
+    let mut output = R::default();
 
     for section in &recipe.sections {
-        if recipe.sections.len() > 1 && section.name.is_some() {
-            latex.add_simple_command(
+        if (options.always_sections || recipe.sections.len() > 1) && section.name.is_some() {
+            output.add_simple_command(
                 "instructionsection",
-                &sanitize_latex(section.name.as_ref().unwrap()),
+                &R::escape(section.name.as_ref().unwrap()),
             );
         }
 
         for content in &section.content {
-            let instruction = match content {
-                Content::Step(step) => step_text(recipe, step),
-                Content::Text(text) => text.clone(),
+            match content {
+                Content::Step(step) => output
+                    .add_simple_command("step", &step_text::<R>(recipe, step, location, options)),
+                // Text blocks are the `>` notes of the recipe, keep them apart from
+                // the numbered steps
+                Content::Text(text) => {
+                    output.add_simple_command("recipenote", &render_inline::<R>(text))
+                }
             };
-
-            latex.add_simple_command("step", &sanitize_latex(&instruction));
         }
     }
 
-    latex
+    output
 }
 
-fn step_text(recipe: &Recipe, step: &Step) -> String {
-    step.items
+/// Builds the markup of a step, every item is sanitized on its own so the
+/// already formatted quantities are not escaped twice.
+fn step_text<R: RecipeRenderer>(
+    recipe: &Recipe,
+    step: &Step,
+    location: RecipeLocation,
+    options: &FormatOptions,
+) -> String {
+    let text: String = step
+        .items
         .iter()
         .map(|item| match item {
-            Item::Text { value } => value.clone(),
-            Item::Ingredient { index } => recipe.ingredients[*index].display_name().to_string(),
-            Item::Cookware { index } => recipe.cookware[*index].name.clone(),
-            Item::Timer { index } => format_timer(
+            Item::Text { value } => R::escape(value),
+            Item::Ingredient { index } => {
+                ingredient_text::<R>(&recipe.ingredients[*index], location, options)
+            }
+            Item::Cookware { index } => cookware_text::<R>(&recipe.cookware[*index], options),
+            Item::Timer { index } => format_timer::<R>(
                 recipe.timers[*index].quantity.as_ref(),
                 recipe.timers[*index].name.as_deref(),
+                options,
             ),
-            Item::InlineQuantity { index } => format_quantity(&recipe.inline_quantities[*index]),
+            // The value and unit are escaped by format_quantity, e.g. `50 %`
+            // becomes `50 \%`
+            Item::InlineQuantity { index } => {
+                format_quantity::<R>(&recipe.inline_quantities[*index], options)
+            }
         })
-        .collect()
+        .collect();
+
+    normalize_whitespace::<R>(&text, options.step_line_breaks)
 }
 
-fn format_timer(quantity: Option<&Quantity>, name: Option<&str>) -> String {
+/// Collapses runs of whitespace to a single space. With `line_breaks` the
+/// line breaks of a step spanning multiple lines are kept, e.g. as `\newline`.
+fn normalize_whitespace<R: RecipeRenderer>(text: &str, line_breaks: bool) -> String {
+    if !line_breaks {
+        return text.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(&format!(" {} ", R::LINE_BREAK))
+}
+
+/// Ingredients referencing another recipe link to that recipe's label. Every
+/// ingredient is shown in the step, including hidden ones (`@-water`) that
+/// are left out of the ingredient list.
+fn ingredient_text<R: RecipeRenderer>(
+    ingredient: &Ingredient,
+    location: RecipeLocation,
+    options: &FormatOptions,
+) -> String {
+    let mut name = R::escape(&ingredient.display_name());
+
+    if let Some(note) = ingredient.note.as_ref().filter(|_| options.step_notes) {
+        name = format!("{name} ({})", R::escape(note));
+    }
+
+    match &ingredient.reference {
+        Some(reference) => {
+            let label = reference_label(reference, location.collection);
+            R::link(&label, &name)
+        }
+        None => name,
+    }
+}
+
+/// Cookware with a quantity is shown with it, e.g. `2 bowls`.
+fn cookware_text<R: RecipeRenderer>(cookware: &Cookware, options: &FormatOptions) -> String {
+    let name = R::escape(&cookware.name);
+
+    match &cookware.quantity {
+        Some(qty) => format!("{} {name}", format_quantity::<R>(qty, options)),
+        None => name,
+    }
+}
+
+/// Formats a timer, like [`format_quantity`] the result is already
+/// sanitized, including the name (e.g. `50% done`).
+fn format_timer<R: RecipeRenderer>(
+    quantity: Option<&Quantity>,
+    name: Option<&str>,
+    options: &FormatOptions,
+) -> String {
+    let format_duration = |qty: &Quantity| match RecipeTime::quantity_seconds(qty) {
+        Some(seconds) => RecipeTime::format_time::<R>(seconds, &options.time_labels),
+        None => format_quantity::<R>(qty, options),
+    };
+
+    if options.timer_commands {
+        let duration = quantity.map(format_duration).unwrap_or_default();
+        let name = name.map(R::escape).unwrap_or_default();
+
+        let mut output = R::default();
+        output.add_command("timer", &[Arg::required(&duration), Arg::required(&name)]);
+        return output.build();
+    }
+
     match (quantity, name) {
-        (Some(qty), Some(name)) => format!("{} ({name})", format_quantity(qty)),
-        (Some(qty), None) => format_quantity(qty),
-        (None, Some(name)) => name.to_string(),
-        (None, None) => unreachable!("Timer must have either quantity or name"),
+        (Some(qty), Some(name)) => format!("{} ({})", format_duration(qty), R::escape(name)),
+        (Some(qty), None) => format_duration(qty),
+        (None, Some(name)) => R::escape(name),
+        // The parser rejects such timers, but a malformed one shouldn't
+        // abort the whole build
+        (None, None) => {
+            log::warn!("Skipping a timer without a duration or name");
+            String::new()
+        }
     }
 }
 
@@ -397,18 +1701,11 @@ fn get_recipe_note(meta: &Metadata) -> Option<String> {
         .and_then(|note| note.as_str().map(String::from))
 }
 
-pub fn get_collection_name(path: &Path) -> Result<String> {
-    path.file_name()
-        .context("Invalid collection path")?
-        .to_str()
-        .context("Invalid collection name")
-        .map(String::from)
-}
-
 pub fn write_recipe(
     out_dir: &Path,
     collection_name: &str,
     file_name: &str,
+    extension: &str,
     contents: &str,
 ) -> Result<String> {
     let file_stem = Path::new(file_name)
@@ -417,7 +1714,7 @@ pub fn write_recipe(
         .to_str()
         .context("Could not convert to str")?;
 
-    let relative_path = PathBuf::from(collection_name).join(format!("{file_stem}.tex"));
+    let relative_path = PathBuf::from(collection_name).join(format!("{file_stem}.{extension}"));
 
     let target_dir = out_dir.join(collection_name);
     let target_file = out_dir.join(&relative_path);
@@ -431,11 +1728,1076 @@ pub fn write_recipe(
         .map(String::from)
 }
 
-pub fn replace_in_main_tex(out_dir: &Path, new_content: &str) -> Result<()> {
-    let main_tex = out_dir.join("main.tex");
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::testing::{
+        latex, latex_with, parse, quantity, recipe, recipe_with_meta, with_format, Cookbook,
+        LOCATION,
+    };
+
+    #[test]
+    fn difficulty_is_read_from_metadata() {
+        let latex = latex(&recipe_with_meta("difficulty: easy", "Mix."));
+        assert!(latex.contains(r"\recipemeta{2}{}{}{Easy}"), "{latex}");
+    }
+
+    #[test]
+    fn difficulty_defaults_to_moderate() {
+        let latex = latex(&recipe("Mix."));
+        assert!(latex.contains(r"\recipemeta{2}{}{}{Moderate}"), "{latex}");
+    }
+
+    #[test]
+    fn recipes_without_servings_fail_on_their_own() {
+        let cookbook = Cookbook::new();
+        cookbook
+            .add("Breakfast", "pancakes.cook", &recipe("Mix @flour{200%g}."))
+            .add(
+                "Breakfast",
+                "toast.cook",
+                "---\ntitle: Toast\n---\n\nToast the @bread{2}.\n",
+            );
+
+        let transpiled = cookbook.transpile("Breakfast", TranspileOptions::default());
+
+        let titles: Vec<_> = transpiled
+            .recipes
+            .iter()
+            .map(|r| r.title.as_str())
+            .collect();
+        assert_eq!(titles, ["Pancakes"]);
+        assert_eq!(
+            transpiled.failed,
+            [cookbook.collection("Breakfast").join("toast.cook")]
+        );
+    }
+
+    #[test]
+    fn servings_are_left_empty_unless_required() {
+        let options = with_format(FormatOptions {
+            require_servings: false,
+            ..FormatOptions::default()
+        });
+
+        let latex = latex_with("---\ntitle: Toast\n---\n\nToast.\n", &options);
+        assert!(latex.contains(r"\recipemeta{}{}{}{Moderate}"), "{latex}");
+    }
+
+    #[test]
+    fn description_is_optional() {
+        let latex = latex(&recipe("Mix."));
+        assert!(latex.contains(r"\recipeheader{Pancakes}"), "{latex}");
+        assert!(!latex.contains(r"\recipedesc"), "{latex}");
+    }
+
+    #[test]
+    fn description_is_emitted_when_present() {
+        let latex = latex(&recipe_with_meta("description: Fluffy", "Mix."));
+        assert!(latex.contains(r"\recipedesc{Fluffy}"), "{latex}");
+    }
+
+    #[test]
+    fn nutrition_is_emitted_without_trailing_zeros() {
+        let latex = latex(&recipe_with_meta(
+            "calories: 250.0\nprotein: 8.5\nfat: 10\ncarbohydrates: 30",
+            "Mix.",
+        ));
+        assert!(
+            latex.contains(r"\recipenutrition{250}{8.5}{10}{30}"),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn nutrition_is_left_out_when_absent() {
+        let latex = latex(&recipe("Mix."));
+        assert!(!latex.contains(r"\recipenutrition"), "{latex}");
+    }
+
+    #[test]
+    fn tags_keep_their_declared_order() {
+        let latex = latex(&recipe_with_meta("tags: [vegan, quick, one_pot]", "Mix."));
+        assert!(
+            latex.contains(r"\recipetags{vegan, quick, one\_pot}"),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn tags_are_left_out_when_absent() {
+        let latex = latex(&recipe("Mix."));
+        assert!(!latex.contains(r"\recipetags"), "{latex}");
+    }
+
+    #[test]
+    fn scale_multiplies_ingredient_quantities() {
+        let source = recipe("Mix @flour{200%g}.");
+        let options = TranspileOptions {
+            scale: 2.0,
+            ..TranspileOptions::default()
+        };
+
+        let latex = latex_with(&source, &options);
+        assert!(latex.contains(r"\ingredient{400 g flour}"), "{latex}");
+    }
+
+    #[test]
+    fn scale_of_one_leaves_quantities_unchanged() {
+        let latex = latex(&recipe("Mix @flour{200%g}."));
+        assert!(latex.contains(r"\ingredient{200 g flour}"), "{latex}");
+    }
+
+    #[test]
+    fn recipe_references_link_to_their_label() {
+        let latex = written(
+            "Spread the @./jam{} on the toast.",
+            TranspileOptions::default(),
+        );
+        assert!(
+            latex.contains(r"\hyperref[recipe:Breakfast:jam]{"),
+            "{latex}"
+        );
+    }
 
-    let main_tex_contents = io::read_file(&main_tex)?;
-    let new_contents = main_tex_contents.replace(r"%{{recipes}}", new_content);
+    #[test]
+    fn references_to_other_collections_use_their_collection() {
+        let latex = written(
+            "Serve with @../Sauces/tomato{}.",
+            TranspileOptions::default(),
+        );
+        assert!(
+            latex.contains(r"\hyperref[recipe:Sauces:tomato]{"),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn references_resolve_nested_collections() {
+        let nested = written(
+            "Fill the @./Desserts/Cakes/sponge{}.",
+            TranspileOptions::default(),
+        );
+        assert!(
+            nested.contains(r"\hyperref[recipe:Desserts-Cakes:sponge]{"),
+            "{nested}"
+        );
+
+        let sibling = written(
+            "Top with @../Desserts/Cakes/../Pies/crumble{}.",
+            TranspileOptions::default(),
+        );
+        assert!(
+            sibling.contains(r"\hyperref[recipe:Desserts-Pies:crumble]{"),
+            "{sibling}"
+        );
+    }
+
+    #[test]
+    fn source_command_omits_missing_parts() {
+        let author = latex(&recipe_with_meta("author: Jane Doe", "Mix."));
+        assert!(author.contains(r"\recipesource{Jane Doe}{}"), "{author}");
+
+        let source = latex(&recipe_with_meta("source: Family Cookbook", "Mix."));
+        assert!(
+            source.contains(r"\recipesource{}{Family Cookbook}"),
+            "{source}"
+        );
+
+        let both = latex(&recipe_with_meta(
+            "author: Jane Doe\nsource: Family Cookbook",
+            "Mix.",
+        ));
+        assert!(
+            both.contains(r"\recipesource{Jane Doe}{Family Cookbook}"),
+            "{both}"
+        );
+    }
 
-    io::write_file(&main_tex, &new_contents)
+    #[test]
+    fn source_command_is_left_out_without_author_and_source() {
+        let latex = latex(&recipe("Mix."));
+        assert!(!latex.contains(r"\recipesource"), "{latex}");
+    }
+
+    #[test]
+    fn durations_use_the_largest_units() {
+        let labels = TimeLabels::default();
+        assert_eq!(
+            RecipeTime::format_time::<LatexBuilder>(30, &labels),
+            "30 secs"
+        );
+        assert_eq!(
+            RecipeTime::format_minutes::<LatexBuilder>(1500, &labels),
+            "1 day 1 hr"
+        );
+        assert_eq!(
+            RecipeTime::format_minutes::<LatexBuilder>(1440, &labels),
+            "1 day"
+        );
+    }
+
+    #[test]
+    fn timers_in_seconds_read_naturally() {
+        let latex = latex(&recipe("Stir for ~{90%seconds}."));
+        assert!(latex.contains("Stir for 1 min 30 secs."), "{latex}");
+    }
+
+    #[test]
+    fn durations_use_custom_labels() {
+        let labels = TimeLabels {
+            second: "s".to_string(),
+            seconds: "s".to_string(),
+            minute: "min".to_string(),
+            minutes: "min".to_string(),
+            hour: "h".to_string(),
+            hours: "h".to_string(),
+            day: "jour".to_string(),
+            days: "jours".to_string(),
+        };
+        assert_eq!(
+            RecipeTime::format_minutes::<LatexBuilder>(150, &labels),
+            "2 h 30 min"
+        );
+        assert_eq!(
+            RecipeTime::format_minutes::<LatexBuilder>(2880, &labels),
+            "2 jours"
+        );
+    }
+
+    #[test]
+    fn preparation_notes_are_kept_in_the_ingredient_list() {
+        let latex = latex(&recipe("Chop @onion{1}(finely diced)."));
+        assert!(
+            latex.contains(r"\ingredient{1 onion, finely diced}"),
+            "{latex}"
+        );
+        assert!(latex.contains(r"\step{Chop onion.}"), "{latex}");
+    }
+
+    #[test]
+    fn preparation_notes_are_shown_in_steps_with_step_notes() {
+        let options = with_format(FormatOptions {
+            step_notes: true,
+            ..FormatOptions::default()
+        });
+        let latex = latex_with(&recipe("Chop @onion{1}(finely diced)."), &options);
+        assert!(
+            latex.contains(r"\step{Chop onion (finely diced).}"),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn notes_are_kept_apart_from_steps() {
+        let latex = latex(&recipe(
+            "> tip: rest the batter first\n\nMix @flour{200%g}.",
+        ));
+        assert!(
+            latex.contains(r"\recipenote{tip: rest the batter first}"),
+            "{latex}"
+        );
+        assert!(!latex.contains(r"\step{tip"), "{latex}");
+        assert!(latex.contains(r"\step{Mix flour.}"), "{latex}");
+    }
+
+    const TWO_SECTIONS: &str = "== Dough ==\n\nMix @flour{200%g} and @sugar{1%cup}.\n\n\
+                                == Topping ==\n\nSprinkle @flour{50%g} and @sugar{50%g}.";
+
+    #[test]
+    fn ingredients_are_grouped_per_section_by_default() {
+        let latex = latex(&recipe(TWO_SECTIONS));
+        assert!(latex.contains(r"\ingredientsection{Dough}"), "{latex}");
+        assert!(latex.contains(r"\ingredient{200 g flour}"), "{latex}");
+        assert!(latex.contains(r"\ingredientsection{Topping}"), "{latex}");
+        assert!(latex.contains(r"\ingredient{50 g flour}"), "{latex}");
+    }
+
+    #[test]
+    fn merged_ingredients_are_summed_across_sections() {
+        let options = with_format(FormatOptions {
+            merge_ingredients: true,
+            ..FormatOptions::default()
+        });
+        let latex = latex_with(&recipe(TWO_SECTIONS), &options);
+        assert!(!latex.contains(r"\ingredientsection"), "{latex}");
+        assert_eq!(latex.matches(r"\ingredient{").count(), 2, "{latex}");
+        assert!(latex.contains(r"\ingredient{250 g flour}"), "{latex}");
+
+        // Incompatible units stay apart
+        let sugar = latex
+            .lines()
+            .find(|line| line.contains(r"\ingredient{") && line.contains("sugar"))
+            .unwrap();
+        assert!(sugar.contains("1 cup") && sugar.contains("50 g"), "{sugar}");
+    }
+
+    #[test]
+    fn ingredients_are_grouped_ignoring_case() {
+        let latex = latex(&recipe("Add @Salt{1%tsp}.\n\nSeason with @salt{1%tsp}."));
+        assert_eq!(latex.matches(r"\ingredient{").count(), 1, "{latex}");
+        assert!(latex.contains(r"\ingredient{2 tsp Salt}"), "{latex}");
+    }
+
+    /// The LaTeX written for a recipe with `body` as its steps, transpiled
+    /// with `options`.
+    fn written(body: &str, options: TranspileOptions) -> String {
+        let cookbook = Cookbook::new();
+        cookbook.add("Breakfast", "pancakes.cook", &recipe(body));
+        cookbook.transpile("Breakfast", options);
+
+        std::fs::read_to_string(cookbook.output_dir().join("Breakfast/pancakes.tex"))
+            .expect("failed to read the recipe")
+    }
+
+    #[test]
+    fn quantities_of_further_systems_follow_in_parentheses() {
+        let options = TranspileOptions {
+            convert_systems: vec![System::Metric, System::Imperial],
+            ..TranspileOptions::default()
+        };
+        let latex = latex_with(&recipe("Mix @flour{200%g}."), &options);
+        let flour = latex
+            .lines()
+            .find(|line| line.contains(r"\ingredient{"))
+            .unwrap();
+        assert!(flour.contains(r"\ingredient{200 g ("), "{flour}");
+        assert!(flour.contains(" oz) flour}"), "{flour}");
+    }
+
+    #[test]
+    fn quantities_are_converted_without_a_units_file() {
+        let options = TranspileOptions {
+            convert_systems: vec![System::Imperial],
+            ..TranspileOptions::default()
+        };
+        let latex = latex_with(&recipe("Mix @flour{200%g}."), &options);
+        assert!(!latex.contains("200 g"), "{latex}");
+        assert!(latex.contains(" oz flour}"), "{latex}");
+    }
+
+    #[test]
+    fn tag_filters_select_recipes() {
+        let cookbook = Cookbook::new();
+        cookbook
+            .add(
+                "Mains",
+                "salad.cook",
+                &recipe_with_meta("tags: [vegan]", "Toss."),
+            )
+            .add(
+                "Mains",
+                "steak.cook",
+                &recipe_with_meta("tags: [meat]", "Fry."),
+            );
+
+        let vegan = cookbook.transpile(
+            "Mains",
+            TranspileOptions {
+                filter_tags: vec!["vegan".to_string()],
+                ..TranspileOptions::default()
+            },
+        );
+        let paths: Vec<_> = vegan.recipes.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["Mains/salad.tex"]);
+        assert!(vegan.failed.is_empty());
+
+        let without_meat = cookbook.transpile(
+            "Mains",
+            TranspileOptions {
+                exclude_tags: vec!["meat".to_string()],
+                ..TranspileOptions::default()
+            },
+        );
+        let paths: Vec<_> = without_meat
+            .recipes
+            .iter()
+            .map(|r| r.path.as_str())
+            .collect();
+        assert_eq!(paths, ["Mains/salad.tex"]);
+    }
+
+    #[test]
+    fn servings_scale_to_the_requested_amount() {
+        let options = TranspileOptions {
+            servings: Some(6),
+            ..TranspileOptions::default()
+        };
+        let latex = latex_with(&recipe("Mix @flour{200%g} and @milk{1.5%cup}."), &options);
+        assert!(latex.contains(r"\ingredient{600 g flour}"), "{latex}");
+        assert!(latex.contains(r"\ingredient{4.5 cup milk}"), "{latex}");
+    }
+
+    #[test]
+    fn single_section_header_needs_always_sections() {
+        let source = recipe("== Dough ==\n\nMix @flour{200%g}.");
+        let latex = latex(&source);
+        assert!(!latex.contains(r"\instructionsection"), "{latex}");
+
+        let options = with_format(FormatOptions {
+            always_sections: true,
+            ..FormatOptions::default()
+        });
+        let always = latex_with(&source, &options);
+        assert!(always.contains(r"\instructionsection{Dough}"), "{always}");
+    }
+
+    #[test]
+    fn ingredients_keep_the_position_of_their_first_mention() {
+        let latex = latex(&recipe(
+            "Mix @flour{100%g} and @milk{1%cup}.\n\nAdd @eggs{2}, then @flour{50%g}.",
+        ));
+        let ingredients: Vec<_> = latex
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with(r"\ingredient{"))
+            .collect();
+        assert_eq!(
+            ingredients,
+            [
+                r"\ingredient{150 g flour}",
+                r"\ingredient{1 cup milk}",
+                r"\ingredient{2 eggs}",
+            ]
+        );
+    }
+
+    #[test]
+    fn recipe_is_transpiled_from_a_string() {
+        let latex = latex(&recipe("Mix @flour{200%g}."));
+        assert!(latex.contains(r"\recipeheader{Pancakes}"), "{latex}");
+        assert!(latex.contains(r"\begin{recipe}"), "{latex}");
+        assert!(!latex.contains(r"\recipeimage"), "{latex}");
+    }
+
+    #[test]
+    fn inline_quantities_are_escaped_once() {
+        let mut recipe = parse(&recipe("Add extra."));
+        recipe.inline_quantities.push(quantity(50.0, Some("%")));
+        let Content::Step(step) = &mut recipe.sections[0].content[0] else {
+            panic!("expected a step");
+        };
+        step.items = vec![
+            Item::Text {
+                value: "Add ".to_string(),
+            },
+            Item::InlineQuantity { index: 0 },
+            Item::Text {
+                value: " extra.".to_string(),
+            },
+        ];
+
+        let Content::Step(step) = &recipe.sections[0].content[0] else {
+            unreachable!();
+        };
+        let text = step_text::<LatexBuilder>(&recipe, step, LOCATION, &FormatOptions::default());
+        assert_eq!(text, r"Add 50 \% extra.");
+    }
+
+    #[test]
+    fn servings_ranges_use_an_en_dash() {
+        let latex = latex("---\ntitle: Pancakes\nservings: 4-6\n---\n\nMix.\n");
+        assert!(latex.contains(r"\recipemeta{4--6}"), "{latex}");
+    }
+
+    #[test]
+    fn multiple_servings_are_listed() {
+        let latex = latex("---\ntitle: Pancakes\nservings: 2|4\n---\n\nMix.\n");
+        assert!(latex.contains(r"\recipemeta{2, 4}"), "{latex}");
+    }
+
+    #[test]
+    fn timer_names_are_escaped_once() {
+        let duration = quantity(10.0, Some("minutes"));
+        let plain = format_timer::<LatexBuilder>(
+            Some(&duration),
+            Some("50% done"),
+            &FormatOptions::default(),
+        );
+        assert_eq!(plain, r"10 mins (50\% done)");
+
+        let options = FormatOptions {
+            timer_commands: true,
+            ..FormatOptions::default()
+        };
+        let command = format_timer::<LatexBuilder>(Some(&duration), Some("50% done"), &options);
+        assert_eq!(command, r"\timer{10 mins}{50\% done}");
+
+        for latex in [plain, command] {
+            assert_eq!(latex.matches(r"50\% done").count(), 1, "{latex}");
+            assert!(!latex.contains(r"\textbackslash{}"), "{latex}");
+        }
+    }
+
+    #[test]
+    fn cookware_quantities_are_shown_in_steps() {
+        let latex = latex(&recipe("Grease #pans{3} and a #bowl."));
+        assert!(
+            latex.contains(r"\step{Grease 3 pans and a bowl.}"),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn ingredient_references_are_summed_into_the_original() {
+        let latex = latex(&recipe("Mix @flour{200%g}.\n\nDust with &flour{50%g}."));
+        assert_eq!(latex.matches(r"\ingredient{").count(), 1, "{latex}");
+        assert!(latex.contains(r"\ingredient{250 g flour}"), "{latex}");
+    }
+
+    #[test]
+    fn hidden_ingredients_are_only_shown_in_steps() {
+        let latex = latex(&recipe("Mix @flour{200%g} with @-water{100%ml}."));
+        let ingredients = &latex[latex.find(r"\begin{ingredients}").unwrap()
+            ..latex.find(r"\end{ingredients}").unwrap()];
+        assert!(!ingredients.contains("water"), "{latex}");
+        assert!(latex.contains(r"\step{Mix flour with water.}"), "{latex}");
+    }
+
+    #[test]
+    fn labels_replace_unsafe_characters() {
+        assert_eq!(
+            recipe_label("Desserts", "apple pie"),
+            "recipe:Desserts:apple-pie"
+        );
+        assert_eq!(
+            recipe_label("Desserts/Cakes", "chocolate_cake"),
+            "recipe:Desserts-Cakes:chocolate-cake"
+        );
+    }
+
+    #[test]
+    fn recipes_are_labeled() {
+        let location = RecipeLocation {
+            collection: "Desserts",
+            file_name: "apple pie.cook",
+            stem: "apple pie",
+            number: None,
+        };
+        let latex = create_recipe(
+            &parse(&recipe("Bake.")),
+            &[],
+            &Converter::empty(),
+            location,
+            None,
+            &FormatOptions::default(),
+        )
+        .unwrap();
+        assert!(
+            latex.contains(r"\label{recipe:Desserts:apple-pie}"),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn recipes_without_steps_have_no_instructions() {
+        let latex = latex(&recipe(""));
+        assert!(!latex.contains(r"\begin{instructions}"), "{latex}");
+        assert!(!latex.contains(r"\begin{ingredients}"), "{latex}");
+        assert!(latex.contains(r"\begin{recipe}"), "{latex}");
+        assert!(validate_latex(&latex).is_empty(), "{latex}");
+    }
+
+    #[test]
+    fn split_ingredient_args_separate_quantity_and_name() {
+        let options = with_format(FormatOptions {
+            split_ingredient_args: true,
+            ..FormatOptions::default()
+        });
+        let latex = latex_with(&recipe("Mix @flour{200%g} with @salt."), &options);
+        assert!(latex.contains(r"\ingredient{200 g}{flour}"), "{latex}");
+        assert!(latex.contains(r"\ingredient{}{salt}"), "{latex}");
+    }
+
+    #[test]
+    fn timers_are_wrapped_in_commands() {
+        let options = with_format(FormatOptions {
+            timer_commands: true,
+            ..FormatOptions::default()
+        });
+        let latex = latex_with(
+            &recipe("Bake for ~{10%minutes}.\n\nLet it ~rest{8%hours}."),
+            &options,
+        );
+        assert!(
+            latex.contains(r"\step{Bake for \timer{10 mins}{}.}"),
+            "{latex}"
+        );
+        assert!(
+            latex.contains(r"\step{Let it \timer{8 hrs}{rest}.}"),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn multi_line_steps_are_single_spaced() {
+        let latex = latex(&recipe("Mix the  @flour{200%g}\nwith   the @milk{1%cup}."));
+        assert!(
+            latex.contains(r"\step{Mix the flour with the milk.}"),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn line_breaks_are_kept_on_request() {
+        assert_eq!(
+            normalize_whitespace::<LatexBuilder>("Mix  the flour\n  with the milk.\n", true),
+            r"Mix the flour \newline with the milk."
+        );
+        assert_eq!(
+            normalize_whitespace::<LatexBuilder>("Mix  the flour\n  with the milk.\n", false),
+            "Mix the flour with the milk."
+        );
+    }
+
+    #[test]
+    fn unconvertible_quantities_are_counted() {
+        let cookbook = Cookbook::new();
+        cookbook.add(
+            "Breakfast",
+            "pancakes.cook",
+            &recipe("Mix @flour{200%g} with @salt{1%pinch}."),
+        );
+
+        let options = TranspileOptions {
+            convert_systems: vec![System::Imperial],
+            inline: true,
+            ..TranspileOptions::default()
+        };
+        let transpiled = cookbook.transpile("Breakfast", options);
+        let pancakes = &transpiled.recipes[0];
+        assert_eq!(pancakes.conversion_errors, 1);
+        let latex = pancakes.content.as_deref().unwrap();
+        assert!(latex.contains(r"\ingredient{1 pinch salt}"), "{latex}");
+    }
+
+    #[test]
+    fn unconvertible_quantities_fail_strict_conversions() {
+        let options = TranspileOptions {
+            convert_systems: vec![System::Imperial],
+            convert_strict: true,
+            ..TranspileOptions::default()
+        };
+        let error = transpile_recipe_str(&recipe("Add @salt{1%pinch}."), &options, None)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("1 quantities"), "{error}");
+    }
+
+    #[test]
+    fn custom_metadata_keys_are_passed_through() {
+        let options = with_format(FormatOptions {
+            extra_meta_keys: vec!["oven_temp".to_string(), "missing".to_string()],
+            ..FormatOptions::default()
+        });
+        let latex = latex_with(&recipe_with_meta("oven_temp: 200C", "Bake."), &options);
+        assert!(
+            latex.contains(r"\recipemetaextra{oven\_temp}{200C}"),
+            "{latex}"
+        );
+        assert_eq!(latex.matches(r"\recipemetaextra").count(), 1, "{latex}");
+    }
+
+    #[test]
+    fn yield_is_emitted_next_to_servings() {
+        let with_yield = latex(&recipe_with_meta("yield: 12 muffins", "Bake."));
+        assert!(with_yield.contains(r"\recipemeta{2}"), "{with_yield}");
+        assert!(
+            with_yield.contains(r"\recipeyield{12 muffins}"),
+            "{with_yield}"
+        );
+
+        let without = latex(&recipe("Bake."));
+        assert!(!without.contains(r"\recipeyield"), "{without}");
+    }
+
+    #[test]
+    fn url_sources_become_a_source_url() {
+        let latex = latex(&recipe_with_meta(
+            "source: https://example.com/recipe",
+            "Mix.",
+        ));
+        assert!(
+            latex.contains(r"\recipesourceurl{https://example.com/recipe}"),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn plain_sources_have_no_source_url() {
+        let latex = latex(&recipe_with_meta("source: Family Cookbook", "Mix."));
+        assert!(!latex.contains(r"\recipesourceurl"), "{latex}");
+    }
+
+    #[test]
+    fn hidden_cookware_is_only_shown_in_steps() {
+        let latex = latex(&recipe("Heat in a #-pot{}, stir with a #spoon{}(wooden)."));
+        assert!(!latex.contains(r"\cookware{pot}"), "{latex}");
+        assert!(latex.contains(r"\cookware{spoon, wooden}"), "{latex}");
+        assert!(
+            latex.contains(r"\step{Heat in a pot, stir with a spoon.}"),
+            "{latex}"
+        );
+    }
+
+    #[test]
+    fn hide_meta_leaves_out_the_meta_command() {
+        let hidden = latex(&recipe_with_meta(
+            "hide_meta: true\nprep time: 10 min",
+            "Mix.",
+        ));
+        assert!(!hidden.contains(r"\recipemeta"), "{hidden}");
+        assert!(!hidden.contains(r"\recipetotaltime"), "{hidden}");
+
+        let shown = latex(&recipe_with_meta("hide_meta: false", "Mix."));
+        assert!(shown.contains(r"\recipemeta"), "{shown}");
+    }
+
+    #[test]
+    fn hide_meta_does_not_require_servings() {
+        let latex = latex("---\ntitle: Salsa\nhide_meta: true\n---\n\nMix.\n");
+        assert!(latex.contains(r"\recipeheader{Salsa}"), "{latex}");
+    }
+
+    #[test]
+    fn recipe_title_falls_back_to_the_file_name() {
+        let titleless = parse("---\nservings: 2\n---\n\nMix.\n");
+        let from_filename = FormatOptions {
+            title_from_filename: true,
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            recipe_title(&titleless, "banana-bread", &from_filename).unwrap(),
+            "Banana Bread"
+        );
+        assert_eq!(
+            recipe_title(&titleless, "my_grandmas  pie", &from_filename).unwrap(),
+            "My Grandmas Pie"
+        );
+        assert!(recipe_title(&titleless, "banana-bread", &FormatOptions::default()).is_err());
+    }
+
+    #[test]
+    fn titleless_recipes_are_named_after_their_file() {
+        let cookbook = Cookbook::new();
+        cookbook.add(
+            "Baking",
+            "banana-bread.cook",
+            "---\nservings: 8\n---\n\nMash the @bananas{3}.\n",
+        );
+
+        let strict = cookbook.transpile("Baking", TranspileOptions::default());
+        assert!(strict.recipes.is_empty());
+        assert_eq!(strict.failed.len(), 1);
+
+        let transpiled = cookbook.transpile(
+            "Baking",
+            with_format(FormatOptions {
+                title_from_filename: true,
+                ..FormatOptions::default()
+            }),
+        );
+        assert_eq!(transpiled.recipes[0].title, "Banana Bread");
+
+        let latex =
+            std::fs::read_to_string(cookbook.output_dir().join("Baking/banana-bread.tex")).unwrap();
+        assert!(latex.contains(r"\recipeheader{Banana Bread}"), "{latex}");
+    }
+
+    #[test]
+    fn normalize_units_sums_compatible_units() {
+        let source = recipe("Mix @flour{100%g}.\n\nFold in @flour{0.1%kg}.");
+        let normalize = with_format(FormatOptions {
+            normalize_units: true,
+            ..FormatOptions::default()
+        });
+
+        let normalized = latex_with(&source, &normalize);
+        assert_eq!(
+            normalized.matches(r"\ingredient{").count(),
+            1,
+            "{normalized}"
+        );
+        assert!(
+            normalized.contains(r"\ingredient{200 g flour}"),
+            "{normalized}"
+        );
+
+        let separate = latex(&source);
+        assert!(
+            separate.contains(r"\ingredient{100 g, 0.1 kg flour}"),
+            "{separate}"
+        );
+    }
+
+    #[test]
+    fn normalize_units_shows_the_most_fitting_unit() {
+        let latex = latex_with(
+            &recipe("Mix @flour{600%g}.\n\nFold in @flour{0.5%kg}."),
+            &with_format(FormatOptions {
+                normalize_units: true,
+                ..FormatOptions::default()
+            }),
+        );
+        assert!(latex.contains(r"\ingredient{1.1 kg flour}"), "{latex}");
+    }
+
+    #[test]
+    fn scale_multiplies_inline_quantities() {
+        let mut recipe = parse(&recipe("Add sugar."));
+        recipe.inline_quantities.push(quantity(50.0, Some("g")));
+        let Content::Step(step) = &mut recipe.sections[0].content[0] else {
+            panic!("expected a step");
+        };
+        step.items = vec![
+            Item::Text {
+                value: "Add ".to_string(),
+            },
+            Item::InlineQuantity { index: 0 },
+            Item::Text {
+                value: " sugar.".to_string(),
+            },
+        ];
+        let options = TranspileOptions {
+            scale: 2.0,
+            ..TranspileOptions::default()
+        };
+
+        let scaled = scale_and_convert(recipe, &options, &Converter::empty(), "pancakes.cook")
+            .unwrap()
+            .recipe;
+        let Content::Step(step) = &scaled.sections[0].content[0] else {
+            unreachable!();
+        };
+        let text = step_text::<LatexBuilder>(&scaled, step, LOCATION, &FormatOptions::default());
+        assert_eq!(text, "Add 100 g sugar.");
+    }
+
+    #[test]
+    fn timers_are_only_scaled_with_scale_timers() {
+        let source = recipe("Mix @flour{200%g} and rest for ~{10%minutes}.");
+        let scaled = TranspileOptions {
+            scale: 2.0,
+            ..TranspileOptions::default()
+        };
+
+        let fixed = latex_with(&source, &scaled);
+        assert!(fixed.contains(r"\ingredient{400 g flour}"), "{fixed}");
+        assert!(fixed.contains("rest for 10 mins."), "{fixed}");
+
+        let with_timers = latex_with(
+            &source,
+            &TranspileOptions {
+                scale_timers: true,
+                ..scaled
+            },
+        );
+        assert!(with_timers.contains("rest for 20 mins."), "{with_timers}");
+    }
+
+    #[test]
+    fn ingredient_columns_wrap_the_list_in_multicols() {
+        let source = recipe("Mix @flour{200%g} and @milk{300%ml}.");
+        let two_columns = with_format(FormatOptions {
+            ingredient_columns: Some(2),
+            ..FormatOptions::default()
+        });
+
+        let columns = latex_with(&source, &two_columns);
+        assert!(columns.contains(r"\begin{multicols}{2}"), "{columns}");
+        assert!(columns.contains(r"\end{multicols}"), "{columns}");
+
+        let single = latex(&source);
+        assert!(!single.contains("multicols"), "{single}");
+    }
+
+    #[test]
+    fn ingredient_columns_in_the_metadata_override_the_option() {
+        let two_columns = with_format(FormatOptions {
+            ingredient_columns: Some(2),
+            ..FormatOptions::default()
+        });
+
+        let off = latex_with(
+            &recipe_with_meta("ingredient_columns: 1", "Mix @flour{200%g}."),
+            &two_columns,
+        );
+        assert!(!off.contains("multicols"), "{off}");
+
+        let three = latex(&recipe_with_meta(
+            "ingredient_columns: 3",
+            "Mix @flour{200%g}.",
+        ));
+        assert!(three.contains(r"\begin{multicols}{3}"), "{three}");
+    }
+
+    #[test]
+    fn layout_selects_the_recipe_environment() {
+        let compact = latex(&recipe_with_meta("layout: compact", "Mix."));
+        assert!(compact.contains(r"\begin{recipecompact}"), "{compact}");
+        assert!(compact.contains(r"\end{recipecompact}"), "{compact}");
+
+        let unknown = latex(&recipe_with_meta("layout: poster", "Mix."));
+        assert!(unknown.contains(r"\begin{recipe}"), "{unknown}");
+    }
+
+    #[test]
+    fn layouts_are_configurable() {
+        let options = with_format(FormatOptions {
+            layouts: HashMap::from([("drink".to_string(), "recipedrink".to_string())]),
+            ..FormatOptions::default()
+        });
+        let latex = latex_with(&recipe_with_meta("layout: drink", "Shake."), &options);
+        assert!(latex.contains(r"\begin{recipedrink}"), "{latex}");
+    }
+
+    #[test]
+    fn timers_without_duration_or_name_are_skipped() {
+        assert_eq!(
+            format_timer::<LatexBuilder>(None, None, &FormatOptions::default()),
+            ""
+        );
+        assert_eq!(
+            format_timer::<LatexBuilder>(None, Some("rest"), &FormatOptions::default()),
+            "rest"
+        );
+    }
+
+    #[test]
+    fn ingredient_ranges_use_an_en_dash() {
+        let latex = latex(&recipe("Crush @garlic{2-3%cloves} and @chili{1-2}."));
+        assert!(
+            latex.contains(r"\ingredient{2--3 cloves garlic}"),
+            "{latex}"
+        );
+        assert!(latex.contains(r"\ingredient{1--2 chili}"), "{latex}");
+    }
+
+    #[test]
+    fn alpha_sort_differs_from_mention_order() {
+        let source = recipe("Whisk @milk{300%ml}, @Eggs{2} and @flour{200%g}, then add @butter{}.");
+        let names = |latex: &str| {
+            latex
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix(r"\ingredient{"))
+                .map(|rest| rest.trim_end_matches('}').to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let mention = latex(&source);
+        assert_eq!(
+            names(&mention),
+            ["300 ml milk", "2 Eggs", "200 g flour", "butter"]
+        );
+
+        let alpha = latex_with(
+            &source,
+            &with_format(FormatOptions {
+                ingredient_sort: IngredientSort::Alpha,
+                ..FormatOptions::default()
+            }),
+        );
+        assert_eq!(
+            names(&alpha),
+            ["butter", "2 Eggs", "200 g flour", "300 ml milk"]
+        );
+    }
+
+    #[test]
+    fn durations_are_parsed_into_minutes() {
+        for (text, minutes) in [
+            ("90", 90),
+            ("90 min", 90),
+            ("90 Minutes", 90),
+            ("1h30m", 90),
+            ("1h 30m", 90),
+            ("1 hour 30 minutes", 90),
+            ("1 hr, 30 mins", 90),
+            ("1:30", 90),
+            ("1.5 hours", 90),
+            ("2 days", 2880),
+            ("90 s", 2),
+        ] {
+            assert_eq!(RecipeTime::parse_minutes(text), Some(minutes), "{text}");
+        }
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        for text in ["", "soon", "1 fortnight", "1:xx", "0 min"] {
+            assert_eq!(RecipeTime::parse_minutes(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn time_aliases_are_read() {
+        for meta in [
+            "prep_time: 1h 30m",
+            "prep time: 90 min",
+            "preptime: 1:30",
+            "time:\n  prep: 1 hour 30 minutes",
+        ] {
+            let latex = latex(&recipe_with_meta(meta, "Mix."));
+            assert!(
+                latex.contains(r"\recipemeta{2}{1 hr 30 mins}{}"),
+                "{meta}: {latex}"
+            );
+        }
+    }
+
+    #[test]
+    fn total_time_adds_prep_and_cook_time() {
+        let latex = latex(&recipe_with_meta("prep time: 20\ncook time: 40", "Mix."));
+        assert!(latex.contains(r"\recipetotaltime{1 hr}"), "{latex}");
+    }
+
+    #[test]
+    fn total_time_is_the_known_time_or_left_out() {
+        let prep = latex(&recipe_with_meta("prep time: 20", "Mix."));
+        assert!(prep.contains(r"\recipetotaltime{20 mins}"), "{prep}");
+
+        let cook = latex(&recipe_with_meta("cook time: 1h 5m", "Bake."));
+        assert!(cook.contains(r"\recipetotaltime{1 hr 5 mins}"), "{cook}");
+
+        let neither = latex(&recipe("Mix."));
+        assert!(!neither.contains(r"\recipetotaltime"), "{neither}");
+    }
+
+    #[test]
+    fn convert_none_in_the_metadata_keeps_the_units() {
+        let metric = TranspileOptions {
+            convert_systems: vec![System::Metric],
+            ..TranspileOptions::default()
+        };
+
+        let converted = latex_with(&recipe("Mix @flour{8%oz}."), &metric);
+        assert!(
+            !converted.contains(r"\ingredient{8 oz flour}"),
+            "{converted}"
+        );
+        assert!(converted.contains(" g flour}"), "{converted}");
+
+        let kept = latex_with(
+            &recipe_with_meta("convert: none", "Mix @flour{8%oz}."),
+            &metric,
+        );
+        assert!(kept.contains(r"\ingredient{8 oz flour}"), "{kept}");
+    }
+
+    #[test]
+    fn convert_system_in_the_metadata_overrides_the_option() {
+        let metric = TranspileOptions {
+            convert_systems: vec![System::Metric],
+            ..TranspileOptions::default()
+        };
+        let latex = latex_with(
+            &recipe_with_meta("convert: imperial", "Mix @flour{200%g}."),
+            &metric,
+        );
+        assert!(!latex.contains(r"\ingredient{200 g flour}"), "{latex}");
+        assert!(latex.contains(" oz flour}"), "{latex}");
+    }
 }
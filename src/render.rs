@@ -0,0 +1,267 @@
+use crate::latex::Arg;
+
+/// The markup operations recipes are rendered with. Every output format
+/// implements it, so [`render_recipe`](crate::recipe::render_recipe) is
+/// shared between them.
+pub trait RecipeRenderer: Default {
+    /// Separates the digit groups of large numbers without breaking the
+    /// number across lines.
+    const THIN_SPACE: &'static str;
+
+    /// Breaks the line within a paragraph, e.g. within a step.
+    const LINE_BREAK: &'static str;
+
+    /// The optional argument marking optional ingredients and cookware, if
+    /// the format has optional arguments.
+    const OPTIONAL_FLAG: Option<&'static str>;
+
+    /// Escapes `text` so it is shown as is.
+    fn escape(text: &str) -> String;
+
+    /// Shows the fraction `numerator`/`denominator`, e.g. the `1/2` of `1/2 cup`.
+    fn fraction(numerator: u32, denominator: u32) -> String;
+
+    /// Escapes `url` so it can be passed to a command as is.
+    fn escape_url(url: &str) -> String;
+
+    /// Links the already escaped `text` to the recipe `label`, see
+    /// [`add_label`](Self::add_label).
+    fn link(label: &str, text: &str) -> String;
+
+    fn add_command(&mut self, command: &str, args: &[Arg]) -> &mut Self;
+
+    fn add_env(&mut self, env: &str, content: &Self) -> &mut Self;
+
+    /// Sets `content` in `columns` columns.
+    fn add_columns(&mut self, columns: usize, content: &Self) -> &mut Self;
+
+    /// Marks the current position with `label`, e.g. `recipe:Desserts:pie`.
+    fn add_label(&mut self, label: &str) -> &mut Self;
+
+    /// Adds `raw` verbatim, the caller is responsible for it being valid markup.
+    fn add_raw(&mut self, raw: &str) -> &mut Self;
+
+    fn add_comment(&mut self, text: &str) -> &mut Self;
+
+    /// Includes the generated file at `path`, relative to the main file.
+    fn add_include(&mut self, path: &str) -> &mut Self;
+
+    fn add_page_break(&mut self) -> &mut Self;
+
+    fn build(&self) -> String;
+
+    fn is_empty(&self) -> bool;
+
+    /// Shows the already escaped `text` in bold.
+    fn bold(text: &str) -> String;
+
+    /// Shows the already escaped `text` in italics.
+    fn italic(text: &str) -> String;
+
+    /// Shows `text` as code, unlike [`bold`](Self::bold) the text is not
+    /// escaped yet.
+    fn code(text: &str) -> String;
+
+    fn add_simple_command(&mut self, command: &str, arg: &str) -> &mut Self {
+        self.add_command(command, &[Arg::required(arg)])
+    }
+}
+
+/// Converts the paragraphs of a minimal Markdown document, see
+/// [`render_inline`] for the supported markup.
+pub fn render_markdown<R: RecipeRenderer>(markdown: &str) -> String {
+    let mut paragraphs = Vec::new();
+    let mut lines = Vec::new();
+
+    for line in markdown.lines().chain(std::iter::once("")) {
+        if line.trim().is_empty() {
+            if !lines.is_empty() {
+                paragraphs.push(render_inline::<R>(&lines.join(" ")));
+                lines.clear();
+            }
+        } else {
+            lines.push(line.trim());
+        }
+    }
+
+    paragraphs.join("\n\n")
+}
+
+/// Converts the `**bold**`, `*italic*` (or `_italic_`) and `` `code` `` text
+/// of a single Markdown paragraph, everything else is escaped as plain text.
+pub(crate) fn render_inline<R: RecipeRenderer>(text: &str) -> String {
+    let mut output = String::new();
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let marker = if rest.starts_with("**") {
+            "**"
+        } else if c == '*' || c == '`' {
+            &rest[..1]
+        } else if c == '_' && !plain.ends_with(char::is_alphanumeric) {
+            // Not inside a word, e.g. snake_case
+            "_"
+        } else {
+            ""
+        };
+
+        if !marker.is_empty() {
+            let inner = &rest[marker.len()..];
+            if let Some(end) = inner.find(marker).filter(|end| *end > 0) {
+                output.push_str(&R::escape(&plain));
+                plain.clear();
+
+                output.push_str(&match marker {
+                    "**" => R::bold(&R::escape(&inner[..end])),
+                    "`" => R::code(&inner[..end]),
+                    _ => R::italic(&R::escape(&inner[..end])),
+                });
+
+                rest = &inner[end + marker.len()..];
+                continue;
+            }
+        }
+
+        plain.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    output.push_str(&R::escape(&plain));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use cooklang::{Converter, Recipe};
+
+    use super::*;
+    use crate::{
+        format::FormatOptions,
+        recipe::render_recipe,
+        testing::{parse, recipe, LOCATION},
+        LatexBuilder, TypstBuilder,
+    };
+
+    fn typst(recipe: &Recipe, alternates: &[Recipe], options: &FormatOptions) -> String {
+        render_recipe::<TypstBuilder>(
+            recipe,
+            alternates,
+            &Converter::empty(),
+            LOCATION,
+            None,
+            options,
+        )
+        .expect("failed to render test recipe")
+        .build()
+    }
+
+    #[test]
+    fn typst_quantities_follow_the_format_options() {
+        let options = FormatOptions {
+            fractions: true,
+            unit_labels: [("cup".to_string(), "cups".to_string())].into(),
+            ..FormatOptions::default()
+        };
+        let typst = typst(&parse(&recipe("Add @milk{1.5%cup}.")), &[], &options);
+        assert!(typst.contains("#ingredient[1$1/2$ cups milk]"), "{typst}");
+    }
+
+    #[test]
+    fn typst_ingredients_keep_their_note() {
+        let recipe = parse(&recipe("Chop @onion{1}(finely diced)."));
+        let typst = typst(&recipe, &[], &FormatOptions::default());
+        assert!(
+            typst.contains("#ingredient[1 onion, finely diced]"),
+            "{typst}"
+        );
+    }
+
+    #[test]
+    fn typst_ingredients_show_alternate_quantities() {
+        let metric = parse(&recipe("Mix @flour{200%g}."));
+        let imperial = parse(&recipe("Mix @flour{7%oz}."));
+        let typst = typst(&metric, &[imperial], &FormatOptions::default());
+        assert!(typst.contains("#ingredient[200 g (7 oz) flour]"), "{typst}");
+    }
+
+    #[test]
+    fn typst_cookware_shows_its_quantity() {
+        let typst = typst(
+            &parse(&recipe("Use #pans{2}.")),
+            &[],
+            &FormatOptions::default(),
+        );
+        assert!(typst.contains("#cookware[2 pans]"), "{typst}");
+        assert!(typst.contains("#step[Use 2 pans.]"), "{typst}");
+    }
+
+    #[test]
+    fn typst_references_link_to_their_label() {
+        let typst = typst(
+            &parse(&recipe("Spread the @./jam{}.")),
+            &[],
+            &FormatOptions::default(),
+        );
+        assert!(
+            typst.contains("#metadata(none) <recipe:Breakfast:pancakes>"),
+            "{typst}"
+        );
+        assert!(
+            typst.contains("#link(<recipe:Breakfast:jam>)[jam]"),
+            "{typst}"
+        );
+    }
+
+    #[test]
+    fn typst_timers_are_formatted_like_latex() {
+        let options = FormatOptions {
+            timer_commands: true,
+            ..FormatOptions::default()
+        };
+        let typst = typst(&parse(&recipe("Rest ~dough{90%min}.")), &[], &options);
+        assert!(typst.contains("#timer[1 hr 30 mins][dough]"), "{typst}");
+    }
+
+    #[test]
+    fn inline_markdown_becomes_latex() {
+        assert_eq!(
+            render_inline::<LatexBuilder>("A *light* and **fluffy** stack, see `notes_v2` & more"),
+            r"A \emph{light} and \textbf{fluffy} stack, see \texttt{notes\_v2} \& more"
+        );
+    }
+
+    #[test]
+    fn underscores_within_words_are_not_emphasis() {
+        assert_eq!(
+            render_inline::<LatexBuilder>("Use the snake_case_name, _not_ this"),
+            r"Use the snake\_case\_name, \emph{not} this"
+        );
+    }
+
+    #[test]
+    fn unclosed_markers_are_kept_as_text() {
+        assert_eq!(render_inline::<LatexBuilder>("5 * 3"), "5 * 3");
+    }
+
+    #[test]
+    fn typst_titleless_recipes_are_named_after_their_file() {
+        let titleless = parse("---\nservings: 2\n---\n\nMix.\n");
+        let options = FormatOptions {
+            title_from_filename: true,
+            ..FormatOptions::default()
+        };
+        let typst = typst(&titleless, &[], &options);
+        assert!(typst.contains("#recipeheader[Pancakes]"), "{typst}");
+
+        let strict = render_recipe::<TypstBuilder>(
+            &titleless,
+            &[],
+            &Converter::empty(),
+            LOCATION,
+            None,
+            &FormatOptions::default(),
+        );
+        assert!(strict.is_err());
+    }
+}
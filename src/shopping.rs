@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use cooklang::{Converter, GroupedQuantity};
+
+use crate::{
+    format::FormatOptions,
+    latex::{sanitize_latex, Arg, LatexBuilder},
+    recipe::{format_grouped_quantity, TranspiledRecipe},
+};
+
+/// Builds an alphabetical shopping list of the ingredients of all recipes.
+/// Ingredients are combined case-insensitively like in the ingredient index,
+/// compatible quantities are summed and the others are listed next to them,
+/// e.g. `500 g, 2 cups` flour.
+pub fn build_shopping_list(
+    recipes: &[TranspiledRecipe],
+    converter: &Converter,
+    options: &FormatOptions,
+) -> LatexBuilder {
+    let mut list: BTreeMap<String, (&str, GroupedQuantity)> = BTreeMap::new();
+
+    for recipe in recipes {
+        for (ingredient, quantity) in &recipe.shopping {
+            let key = ingredient.trim().to_lowercase();
+            let (_, total) = list
+                .entry(key)
+                .or_insert((ingredient.trim(), GroupedQuantity::default()));
+
+            for q in quantity.iter() {
+                total.add(q, converter);
+            }
+        }
+    }
+
+    let mut items = LatexBuilder::new();
+    for (name, quantity) in list.values() {
+        items.add_command(
+            "shoppingitem",
+            &[
+                Arg::required(
+                    &format_grouped_quantity::<LatexBuilder>(quantity, options).unwrap_or_default(),
+                ),
+                Arg::required(&sanitize_latex(name)),
+            ],
+        );
+    }
+
+    let mut latex = LatexBuilder::new();
+    latex.add_env("shoppinglist", &items);
+    latex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{quantity, transpiled};
+
+    /// A recipe of `stem` buying the `items`, given as name, value and unit.
+    fn buying(stem: &str, items: &[(&str, f64, &str)], converter: &Converter) -> TranspiledRecipe {
+        let mut recipe = transpiled("Baking", stem, stem, &[]);
+        recipe.shopping = items
+            .iter()
+            .map(|(name, value, unit)| {
+                let mut total = GroupedQuantity::default();
+                total.add(&quantity(*value, Some(unit)), converter);
+                (name.to_string(), total)
+            })
+            .collect();
+        recipe
+    }
+
+    #[test]
+    fn shared_ingredients_are_summed() {
+        let converter = Converter::bundled();
+        let recipes = [
+            buying(
+                "bread",
+                &[("flour", 200.0, "g"), ("salt", 5.0, "g")],
+                &converter,
+            ),
+            buying(
+                "cake",
+                &[("Flour", 300.0, "g"), ("sugar", 100.0, "g")],
+                &converter,
+            ),
+        ];
+
+        let latex = build_shopping_list(&recipes, &converter, &FormatOptions::default()).build();
+        assert_eq!(latex.matches(r"\shoppingitem{").count(), 3, "{latex}");
+        assert!(latex.contains(r"\shoppingitem{500 g}{flour}"), "{latex}");
+        assert!(latex.contains(r"\shoppingitem{5 g}{salt}"), "{latex}");
+    }
+
+    #[test]
+    fn incompatible_quantities_are_listed_separately() {
+        let converter = Converter::bundled();
+        let recipes = [
+            buying("bread", &[("flour", 200.0, "g")], &converter),
+            buying("cake", &[("flour", 1.0, "cup")], &converter),
+        ];
+
+        let latex = build_shopping_list(&recipes, &converter, &FormatOptions::default()).build();
+        let flour = latex
+            .lines()
+            .find(|line| line.contains("{flour}"))
+            .expect(&latex);
+        assert!(
+            flour.contains("200 g") && flour.contains("1 cup"),
+            "{flour}"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+/// The preamble of a standalone document. It defines every macro the
+/// generated recipes use, so the output compiles without a template folder:
+///
+/// - `\chapter` (one per collection, article has no chapters of its own)
+/// - `\recipeheader`, `\recipeimage`, `\recipetags`, `\recipedesc`,
+///   `\recipesource`, `\recipesourceurl`, `\recipemeta`, `\recipetotaltime`,
+///   `\recipeyield`, `\recipemetaextra`, `\recipenutrition` and `\recipenote`
+/// - the `recipe`, `recipecompact`, `ingredients`, `cookware` and
+///   `instructions` environments
+/// - `\ingredient`, `\ingredientsection`, `\step`, `\timer` and
+///   `\instructionsection`
+/// - the `ingredientindex` environment and `\ingredientindexentry`
+/// - the `shoppinglist` environment and `\shoppingitem`
+///
+/// `\ingredient` takes the quantity and name as one argument, see
+/// [`SPLIT_INGREDIENT`] for `--split-ingredient-args`.
+const PREAMBLE: &str = r"\documentclass{article}
+\usepackage[T1]{fontenc}
+\usepackage[utf8]{inputenc}
+\usepackage{textcomp}
+\usepackage{graphicx}
+\usepackage{nicefrac}
+\usepackage{multicol}
+\usepackage{xparse}
+\usepackage{hyperref}
+
+\setlength{\parindent}{0pt}
+
+\newcommand{\chapter}[1]{\clearpage\section{#1}}
+
+\NewDocumentCommand{\recipeheader}{ m o }{%
+	\phantomsection\addcontentsline{toc}{subsection}{#1}%
+	{\Large\bfseries #1}\IfNoValueF{#2}{\quad\textit{#2}}\par\medskip
+}
+\newcommand{\recipeimage}[1]{%
+	\includegraphics[width=\textwidth,height=0.3\textheight,keepaspectratio]{#1}\par
+}
+\newcommand{\recipetags}[1]{\textsc{#1}\par}
+\newcommand{\recipedesc}[1]{\textit{#1}\par\medskip}
+\newcommand{\recipesource}[2]{{\small #1\quad #2}\par}
+\newcommand{\recipesourceurl}[1]{{\small online}\footnote{\url{#1}}\par}
+\newcommand{\recipemeta}[4]{%
+	\textbf{Serves} #1\quad\textbf{Prep} #2\quad\textbf{Cooking} #3\quad\textbf{Difficulty} #4\par\medskip
+}
+\newcommand{\recipetotaltime}[1]{{\small\textbf{Total} #1}\par}
+\newcommand{\recipeyield}[1]{{\small\textbf{Makes} #1}\par}
+\newcommand{\recipemetaextra}[2]{{\small\textbf{#1} #2}\par}
+\newcommand{\recipenutrition}[4]{%
+	{\small\textbf{Calories} #1\quad\textbf{Protein} #2\quad\textbf{Fat} #3\quad\textbf{Carbs} #4}\par\medskip
+}
+\newcommand{\recipenote}[1]{\par\textit{#1}\par}
+
+\newenvironment{recipe}{}{\par\bigskip}
+\newenvironment{recipecompact}{\small}{\par\medskip}
+
+\newenvironment{ingredients}{\subsubsection*{Ingredients}}{}
+\NewDocumentCommand{\ingredient}{ m O{\BooleanFalse} }{#1\IfBooleanT{#2}{ (optional)}\par}
+\newcommand{\ingredientsection}[1]{\par\smallskip\textsc{#1}\par}
+
+\newenvironment{cookware}{%
+	\subsubsection*{Cookware}%
+	\RenewDocumentCommand{\cookware}{ m O{\BooleanFalse} }{##1\IfBooleanT{##2}{ (optional)}\par}%
+}{}
+
+\newcounter{stepnumber}
+\newenvironment{instructions}{\subsubsection*{Instructions}\setcounter{stepnumber}{0}}{}
+\newcommand{\step}[1]{\stepcounter{stepnumber}\par\textbf{\thestepnumber.}~#1\par}
+\NewDocumentCommand{\timer}{ m m }{\IfBlankTF{#1}{#2}{\textit{#1}\IfBlankF{#2}{ (#2)}}}
+\newcommand{\instructionsection}[1]{\par\smallskip\textbf{#1}\par\setcounter{stepnumber}{0}}
+
+\newenvironment{ingredientindex}{\clearpage\section*{Ingredient index}}{}
+\newcommand{\ingredientindexentry}[2]{\textbf{#1}\quad\textit{#2}\par}
+
+\newenvironment{shoppinglist}{\clearpage\section*{Shopping list}}{}
+\newcommand{\shoppingitem}[2]{\IfBlankF{#1}{#1 }#2\par}
+";
+
+/// Redefines `\ingredient` with the quantity and the name as separate
+/// arguments, as written with `--split-ingredient-args`.
+const SPLIT_INGREDIENT: &str = r"\RenewDocumentCommand{\ingredient}{ m m O{\BooleanFalse} }{\IfBlankF{#1}{#1 }#2\IfBooleanT{#3}{ (optional)}\par}
+";
+
+/// Wraps `body` in a complete, compilable document. `preamble` is added after
+/// the macro definitions, before the document begins.
+pub fn document(preamble: &str, body: &str, split_ingredient_args: bool) -> String {
+    let ingredient = if split_ingredient_args {
+        SPLIT_INGREDIENT
+    } else {
+        ""
+    };
+    format!("{PREAMBLE}{ingredient}\n{preamble}\n\\begin{{document}}\n\\tableofcontents\n\n{body}\n\n\\end{{document}}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use cooklang::{Converter, GroupedQuantity};
+
+    use super::*;
+    use crate::{
+        format::FormatOptions,
+        index::build_ingredient_index,
+        shopping::build_shopping_list,
+        testing::{latex_with, quantity, transpiled, with_format},
+    };
+
+    /// Commands and environments defined by LaTeX itself or the packages the
+    /// preamble loads.
+    const BUILTIN: [&str; 19] = [
+        "begin",
+        "end",
+        "label",
+        "pagebreak",
+        "newline",
+        "hyperref",
+        "pageref",
+        "nicefrac",
+        "BooleanTrue",
+        "textbf",
+        "emph",
+        "texttt",
+        "textbackslash",
+        "textasciitilde",
+        "textasciicircum",
+        "textdegree",
+        "section",
+        "subsection",
+        "subsubsection",
+    ];
+
+    /// The number of required and optional arguments of every command the
+    /// `document` defines, and the environments it defines.
+    fn definitions(document: &str) -> (HashMap<String, (usize, usize)>, HashSet<String>) {
+        let mut commands = HashMap::new();
+        let mut environments = HashSet::new();
+
+        for line in document.lines().map(str::trim_start) {
+            if let Some(rest) = line.strip_prefix("\\newenvironment{") {
+                environments.insert(rest[..rest.find('}').unwrap()].to_string());
+            } else if let Some(rest) = line.strip_prefix("\\newcommand{\\") {
+                let name = &rest[..rest.find('}').unwrap()];
+                let rest = &rest[name.len() + 1..];
+                let required = match rest.strip_prefix('[') {
+                    Some(count) => count[..count.find(']').unwrap()].parse().unwrap(),
+                    None => 0,
+                };
+                commands.insert(name.to_string(), (required, 0));
+            } else if let Some(rest) = line
+                .strip_prefix("\\NewDocumentCommand{\\")
+                .or_else(|| line.strip_prefix("\\RenewDocumentCommand{\\"))
+            {
+                let name = &rest[..rest.find('}').unwrap()];
+                let spec = &rest[name.len() + 2..];
+                let spec = &spec[..spec.find(" }").unwrap()];
+                let required = spec.split_whitespace().filter(|arg| *arg == "m").count();
+                let optional = spec
+                    .split_whitespace()
+                    .filter(|arg| arg.starts_with(['o', 'O']))
+                    .count();
+                commands.insert(name.to_string(), (required, optional));
+            }
+        }
+
+        (commands, environments)
+    }
+
+    /// Every command used in `latex` with its first required argument and
+    /// the number of required and optional arguments that follow it.
+    fn usages(latex: &str) -> Vec<(String, String, usize, usize)> {
+        let mut usages = Vec::new();
+
+        for (start, _) in latex.match_indices('\\') {
+            let rest = &latex[start + 1..];
+            let name: String = rest.chars().take_while(char::is_ascii_alphabetic).collect();
+            if name.is_empty() {
+                continue;
+            }
+
+            let mut chars = rest[name.len()..].chars().peekable();
+            let (mut first, mut required, mut optional) = (String::new(), 0, 0);
+            while let Some(&open) = chars.peek() {
+                let close = match open {
+                    '{' => '}',
+                    '[' => ']',
+                    _ => break,
+                };
+                chars.next();
+                let mut depth = 0;
+                let mut argument = String::new();
+                for c in chars.by_ref() {
+                    match c {
+                        '{' | '[' => depth += 1,
+                        c if c == close && depth == 0 => break,
+                        '}' | ']' => depth -= 1,
+                        _ => {}
+                    }
+                    argument.push(c);
+                }
+                if open == '{' {
+                    if required == 0 {
+                        first = argument;
+                    }
+                    required += 1;
+                } else {
+                    optional += 1;
+                }
+            }
+            usages.push((name, first, required, optional));
+        }
+
+        usages
+    }
+
+    /// A recipe using every part of the recipe layout.
+    const RECIPE: &str = "---
+title: Pancakes
+servings: 2
+author: Jane Doe
+source: https://example.com/pancakes
+tags: [breakfast]
+description: Fluffy.
+prep time: 10 min
+cook time: 20 min
+difficulty: easy
+yield: 8
+calories: 300
+cuisine: French
+---
+
+== Batter ==
+Mix @flour{1/2%cup}, @?sugar{1%tbsp} and @./jam{} in a #bowl{2} with a #?whisk{}.
+Rest for ~rest{10%min}.
+
+> Keep it cold.
+
+== Cooking ==
+Fry in @butter{10%g}.
+";
+
+    /// The LaTeX of [`RECIPE`], the ingredient index and the shopping list.
+    fn generated(format: FormatOptions) -> String {
+        let recipe = latex_with(RECIPE, &with_format(format.clone()));
+
+        let converter = Converter::bundled();
+        let mut pancakes = transpiled("Breakfast", "pancakes", "Pancakes", &["flour"]);
+        let mut flour = GroupedQuantity::default();
+        flour.add(&quantity(200.0, Some("g")), &converter);
+        pancakes.shopping = vec![("flour".to_string(), flour)];
+        let recipes = [pancakes];
+
+        let index = build_ingredient_index(&recipes).build();
+        let shopping = build_shopping_list(&recipes, &converter, &format).build();
+        format!("{recipe}\n{index}\n{shopping}")
+    }
+
+    fn assert_defined(format: FormatOptions) {
+        let split_ingredient_args = format.split_ingredient_args;
+        let latex = generated(format);
+        let (commands, environments) = definitions(&document("", "", split_ingredient_args));
+
+        for (name, first, required, optional) in usages(&latex) {
+            if name == "begin" || name == "end" {
+                assert!(
+                    environments.contains(&first) || first == "multicols",
+                    "environment {first} is not defined"
+                );
+            } else if !BUILTIN.contains(&name.as_str()) {
+                let (defined_required, defined_optional) = commands
+                    .get(&name)
+                    .unwrap_or_else(|| panic!("\\{name} is not defined"));
+                assert_eq!(required, *defined_required, "arguments of \\{name}");
+                assert!(optional <= *defined_optional, "arguments of \\{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_emitted_macro_is_defined() {
+        assert_defined(FormatOptions {
+            fractions: true,
+            step_notes: true,
+            ingredient_columns: Some(2),
+            always_sections: true,
+            timer_commands: true,
+            extra_meta_keys: vec!["cuisine".to_string()],
+            ..FormatOptions::default()
+        });
+    }
+
+    #[test]
+    fn split_ingredients_are_defined_with_two_arguments() {
+        assert_defined(FormatOptions {
+            split_ingredient_args: true,
+            ..FormatOptions::default()
+        });
+    }
+}
@@ -0,0 +1,134 @@
+//! Fixtures shared by the unit tests.
+
+use std::path::PathBuf;
+
+use cooklang::{
+    quantity::{Number, Value},
+    Converter, CooklangParser, Extensions, Quantity, Recipe,
+};
+use tempfile::TempDir;
+
+use crate::{
+    format::FormatOptions,
+    recipe::{
+        recipe_label, transpile_recipe_str, RecipeLocation, RecipeTranspiler, TranspileOptions,
+        TranspiledCollection, TranspiledRecipe,
+    },
+};
+
+/// A recipe titled `Pancakes` serving 2, with `meta` as further frontmatter
+/// lines and `body` as its steps.
+pub(crate) fn recipe_with_meta(meta: &str, body: &str) -> String {
+    format!("---\ntitle: Pancakes\nservings: 2\n{meta}\n---\n\n{body}\n")
+}
+
+/// A recipe titled `Pancakes` serving 2 with `body` as its steps.
+pub(crate) fn recipe(body: &str) -> String {
+    recipe_with_meta("", body)
+}
+
+/// Where the recipes built by hand in the tests are from.
+pub(crate) const LOCATION: RecipeLocation = RecipeLocation {
+    collection: "Breakfast",
+    file_name: "pancakes.cook",
+    stem: "pancakes",
+    number: None,
+};
+
+/// Parses `source` without converting any units.
+pub(crate) fn parse(source: &str) -> Recipe {
+    let parser = CooklangParser::new(Extensions::all(), Converter::empty());
+    let (recipe, _warnings) = parser
+        .parse(source)
+        .into_result()
+        .expect("failed to parse test recipe");
+    recipe
+}
+
+/// A quantity of `value`, in `unit` if given.
+pub(crate) fn quantity(value: f64, unit: Option<&str>) -> Quantity {
+    Quantity::new(Value::Number(Number::from(value)), unit.map(String::from))
+}
+
+/// The default options with quantities and text formatted like `format`.
+pub(crate) fn with_format(format: FormatOptions) -> TranspileOptions {
+    TranspileOptions {
+        format,
+        ..TranspileOptions::default()
+    }
+}
+
+/// The LaTeX of `source` transpiled with `options`.
+pub(crate) fn latex_with(source: &str, options: &TranspileOptions) -> String {
+    transpile_recipe_str(source, options, None).expect("failed to transpile test recipe")
+}
+
+/// The LaTeX of `source` transpiled with the default options.
+pub(crate) fn latex(source: &str) -> String {
+    latex_with(source, &TranspileOptions::default())
+}
+
+/// A recipe of `collection` as transpiled from `stem.cook`, listing the
+/// `ingredients`, for the aggregations over all recipes.
+pub(crate) fn transpiled(
+    collection: &str,
+    stem: &str,
+    title: &str,
+    ingredients: &[&str],
+) -> TranspiledRecipe {
+    TranspiledRecipe {
+        path: format!("{collection}/{stem}.tex"),
+        title: title.to_string(),
+        collection: collection.to_string(),
+        label: recipe_label(collection, stem),
+        references: Vec::new(),
+        ingredients: ingredients.iter().map(|name| name.to_string()).collect(),
+        shopping: Vec::new(),
+        content: None,
+        conversion_errors: 0,
+    }
+}
+
+/// Recipe collections in a temporary folder, transpiled into an output folder
+/// next to them.
+pub(crate) struct Cookbook {
+    dir: TempDir,
+}
+
+impl Cookbook {
+    pub(crate) fn new() -> Self {
+        Self {
+            dir: tempfile::tempdir().expect("failed to create a temporary folder"),
+        }
+    }
+
+    /// Writes the recipe `file_name` into `collection`, creating the
+    /// collection if needed.
+    pub(crate) fn add(&self, collection: &str, file_name: &str, source: &str) -> &Self {
+        let folder = self.collection(collection);
+        std::fs::create_dir_all(&folder).expect("failed to create the collection");
+        std::fs::write(folder.join(file_name), source).expect("failed to write the recipe");
+        self
+    }
+
+    pub(crate) fn collection(&self, name: &str) -> PathBuf {
+        self.dir.path().join("recipes").join(name)
+    }
+
+    pub(crate) fn output_dir(&self) -> PathBuf {
+        self.dir.path().join("out")
+    }
+
+    /// Transpiles `collection` into the output folder.
+    pub(crate) fn transpile(
+        &self,
+        collection: &str,
+        options: TranspileOptions,
+    ) -> TranspiledCollection {
+        let output_dir = self.output_dir();
+        RecipeTranspiler::new(options, &output_dir, None)
+            .expect("failed to create the transpiler")
+            .transpile_collection(&self.collection(collection))
+            .expect("failed to transpile the collection")
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{latex::Arg, render::RecipeRenderer};
+
+const INDENT: &str = "  ";
+
+/// Builds Typst markup. Commands become function calls taking their
+/// arguments as content blocks, e.g. `#recipeheader[Pancakes]`, and
+/// environments pass their content as a single block.
+#[derive(Default)]
+pub struct TypstBuilder {
+    /// Every line together with its block nesting depth
+    content: Vec<(usize, String)>,
+}
+
+impl TypstBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Typst has no optional arguments, optional arguments are passed as
+    /// content blocks like the required ones.
+    pub fn add_command(&mut self, command: &str, args: &[Arg]) -> &mut Self {
+        let formatted_args: String = if args.is_empty() {
+            "()".to_string()
+        } else {
+            args.iter().map(|arg| format!("[{}]", arg.value)).collect()
+        };
+
+        self.content
+            .push((0, format!("#{command}{formatted_args}")));
+        self
+    }
+
+    pub fn add_simple_command(&mut self, command: &str, arg: &str) -> &mut Self {
+        self.add_command(command, &[Arg::required(arg)])
+    }
+
+    pub fn add_env(&mut self, env: &str, content: &TypstBuilder) -> &mut Self {
+        self.content.push((0, format!("#{env}[")));
+        self.add_nested_builder(content, 1);
+        self.content.push((0, "]".to_string()));
+        self
+    }
+
+    pub fn add_raw(&mut self, raw: &str) -> &mut Self {
+        self.content.push((0, raw.to_string()));
+        self
+    }
+
+    pub fn add_comment(&mut self, text: &str) -> &mut Self {
+        for line in text.lines() {
+            self.content.push((0, format!("// {line}")));
+        }
+        self
+    }
+
+    pub fn add_builder(&mut self, other: &TypstBuilder) -> &mut Self {
+        self.add_nested_builder(other, 0)
+    }
+
+    fn add_nested_builder(&mut self, other: &TypstBuilder, depth: usize) -> &mut Self {
+        self.content.extend(
+            other
+                .content
+                .iter()
+                .map(|(line_depth, line)| (line_depth + depth, line.clone())),
+        );
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
+    pub fn build(&self) -> String {
+        self.content
+            .iter()
+            .map(|(depth, line)| format!("{}{line}", INDENT.repeat(*depth)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl RecipeRenderer for TypstBuilder {
+    // A narrow no-break space
+    const THIN_SPACE: &'static str = "\u{202F}";
+
+    const LINE_BREAK: &'static str = "\\";
+
+    // Typst has no optional arguments, optional items are listed like the
+    // others
+    const OPTIONAL_FLAG: Option<&'static str> = None;
+
+    fn escape(text: &str) -> String {
+        sanitize_typst(text)
+    }
+
+    fn fraction(numerator: u32, denominator: u32) -> String {
+        format!("${numerator}/{denominator}$")
+    }
+
+    fn escape_url(url: &str) -> String {
+        sanitize_typst(url.trim())
+    }
+
+    fn link(label: &str, text: &str) -> String {
+        format!("#link(<{label}>)[{text}]")
+    }
+
+    fn add_command(&mut self, command: &str, args: &[Arg]) -> &mut Self {
+        TypstBuilder::add_command(self, command, args)
+    }
+
+    fn add_env(&mut self, env: &str, content: &Self) -> &mut Self {
+        TypstBuilder::add_env(self, env, content)
+    }
+
+    fn add_columns(&mut self, columns: usize, content: &Self) -> &mut Self {
+        self.content.push((0, format!("#columns({columns})[")));
+        self.add_nested_builder(content, 1);
+        self.content.push((0, "]".to_string()));
+        self
+    }
+
+    /// Labels an invisible element, so `#link` can jump to the recipe.
+    fn add_label(&mut self, label: &str) -> &mut Self {
+        TypstBuilder::add_raw(self, &format!("#metadata(none) <{label}>"))
+    }
+
+    fn add_raw(&mut self, raw: &str) -> &mut Self {
+        TypstBuilder::add_raw(self, raw)
+    }
+
+    fn add_comment(&mut self, text: &str) -> &mut Self {
+        TypstBuilder::add_comment(self, text)
+    }
+
+    fn add_include(&mut self, path: &str) -> &mut Self {
+        let path = path.replace('\\', "\\\\").replace('"', "\\\"");
+        TypstBuilder::add_raw(self, &format!("#include \"{path}\""))
+    }
+
+    fn add_page_break(&mut self) -> &mut Self {
+        TypstBuilder::add_command(self, "pagebreak", &[])
+    }
+
+    fn build(&self) -> String {
+        TypstBuilder::build(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        TypstBuilder::is_empty(self)
+    }
+
+    fn bold(text: &str) -> String {
+        format!("*{text}*")
+    }
+
+    fn italic(text: &str) -> String {
+        format!("_{text}_")
+    }
+
+    fn code(text: &str) -> String {
+        let text = text.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("#raw(\"{text}\")")
+    }
+}
+
+/// Escapes the characters with a meaning in Typst markup. `/` is escaped as
+/// well, since `//` starts a comment.
+pub fn sanitize_typst(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(
+            c,
+            '\\' | '#' | '$' | '*' | '_' | '`' | '<' | '>' | '@' | '[' | ']' | '~' | '/'
+        ) {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+    output
+}
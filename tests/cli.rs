@@ -0,0 +1,681 @@
+mod common;
+
+use common::{failure, stdout, success, Project};
+
+/// A recipe whose prep time is not a duration, which the parser warns about.
+const UNREADABLE_PREP_TIME: &str =
+    "---\ntitle: Pancakes\nservings: 2\nprep time: soon\n---\n\nMix.\n";
+
+#[test]
+fn warnings_are_shown_by_default() {
+    let project = Project::new();
+    project.file("Breakfast/pancakes.cook", UNREADABLE_PREP_TIME);
+
+    let stderr = success(&project.build(&["Breakfast"], &[]));
+    assert!(stderr.contains("pancakes.cook"), "{stderr}");
+    assert!(!stderr.contains("Transpiled"), "{stderr}");
+}
+
+#[test]
+fn quiet_hides_warnings() {
+    let project = Project::new();
+    project.file("Breakfast/pancakes.cook", UNREADABLE_PREP_TIME);
+
+    let stderr = success(&project.build(&["Breakfast"], &["-q"]));
+    assert!(stderr.is_empty(), "{stderr}");
+}
+
+#[test]
+fn verbose_shows_progress() {
+    let project = Project::new();
+    project.recipe("Breakfast", "pancakes.cook", "Mix.");
+
+    let stderr = success(&project.build(&["Breakfast"], &["-v"]));
+    assert!(stderr.contains("to Breakfast/pancakes.tex"), "{stderr}");
+}
+
+#[test]
+fn dry_run_writes_nothing() {
+    let project = Project::new();
+    project.recipe("Breakfast", "pancakes.cook", "Mix @flour{200%g}.");
+
+    let output = project.build(&["Breakfast"], &["--dry-run"]);
+    success(&output);
+    assert!(!project.exists("out"));
+
+    let stdout = stdout(&output);
+    assert!(stdout.contains("Would write"), "{stdout}");
+    assert!(
+        stdout.contains(r"\input{Breakfast/pancakes.tex}"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn invalid_units_file_is_an_error() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file("units.toml", "[quantity\n");
+
+    let stderr = failure(&project.build(&["Breakfast"], &["--units-file", "units.toml"]));
+    assert!(stderr.contains("Failed to load units file"), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn non_empty_output_folder_needs_force() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file("out/notes.txt", "mine");
+
+    let stderr = failure(&project.build(&["Breakfast"], &[]));
+    assert!(stderr.contains("use --force to overwrite"), "{stderr}");
+    assert!(stderr.contains("notes.txt"), "{stderr}");
+    assert!(!project.exists("out/main.tex"));
+
+    success(&project.build(&["Breakfast"], &["--force"]));
+    assert!(project.exists("out/main.tex"));
+    assert_eq!(project.read("out/notes.txt"), "mine");
+}
+
+#[test]
+fn earlier_output_folder_needs_no_force() {
+    let project = Project::new();
+    project.recipe("Breakfast", "pancakes.cook", "Mix.");
+    success(&project.build(&["Breakfast"], &[]));
+
+    project.recipe("Breakfast", "waffles.cook", "Stir.");
+    success(&project.build(&["Breakfast"], &[]));
+    assert!(project.exists("out/Breakfast/waffles.tex"));
+}
+
+#[test]
+fn collection_preamble_is_spliced_into_main_file() {
+    let project = Project::new();
+    project
+        .file(
+            "template/main.tex",
+            "\\documentclass{book}\n%{{preamble}}\n\\begin{document}\n%{{recipes}}\n\\end{document}\n",
+        )
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file("Breakfast/preamble.tex", "\\usepackage{xcolor}")
+        .recipe("Dinner", "soup.cook", "Boil.");
+
+    success(&project.build(&["Breakfast", "Dinner"], &[]));
+    let main = project.read("out/main.tex");
+    assert!(
+        main.starts_with("\\documentclass{book}\n\\usepackage{xcolor}\n\\begin{document}"),
+        "{main}"
+    );
+    assert_eq!(main.matches("\\usepackage{xcolor}").count(), 1, "{main}");
+}
+
+#[test]
+fn collection_given_twice_gets_one_chapter() {
+    let project = Project::new();
+    project.recipe("Breakfast", "pancakes.cook", "Mix.");
+
+    let stderr = success(&project.build(&["Breakfast", "./Breakfast"], &[]));
+    assert!(stderr.contains("was given more than once"), "{stderr}");
+
+    let main = project.read("out/main.tex");
+    assert_eq!(main.matches("\\chapter{Breakfast}").count(), 1, "{main}");
+}
+
+#[test]
+fn no_warnings_hides_parser_diagnostics() {
+    let project = Project::new();
+    project.file("Breakfast/pancakes.cook", UNREADABLE_PREP_TIME);
+
+    let stderr = success(&project.build(&["Breakfast"], &[]));
+    assert!(stderr.contains("pancakes.cook"), "{stderr}");
+
+    let stderr = success(&project.build(&["Breakfast"], &["--force", "--no-warnings"]));
+    let lines: Vec<_> = stderr.lines().collect();
+    assert_eq!(lines.len(), 1, "{stderr}");
+    assert!(lines[0].starts_with("Processed 1 recipes"), "{stderr}");
+}
+
+#[test]
+fn summary_counts_failed_recipes_and_collections() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file("Breakfast/broken.cook", "Mix without a title.\n")
+        .file("Empty/notes.txt", "nothing to cook");
+
+    let stderr = success(&project.build(&["Breakfast", "Empty"], &[]));
+    assert!(
+        stderr.contains("Processed 2 recipes across 2 collections (1 failed)"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("broken.cook"), "{stderr}");
+    assert!(stderr.contains("1 collections failed"), "{stderr}");
+}
+
+#[test]
+fn strict_fails_on_a_broken_recipe() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file("Breakfast/broken.cook", "Mix without a title.\n");
+
+    success(&project.build(&["Breakfast"], &[]));
+
+    let stderr = failure(&project.build(&["Breakfast"], &["--force", "--strict"]));
+    assert!(
+        stderr.contains("1 recipes and 0 collections failed to transpile"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("broken.cook"), "{stderr}");
+}
+
+#[test]
+fn collection_intro_follows_the_chapter() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file("Breakfast/intro.md", "The **first** meal.\n");
+
+    success(&project.build(&["Breakfast"], &[]));
+    let main = project.read("out/main.tex");
+    let chapter = main.find("\\chapter{Breakfast}").expect(&main);
+    let intro = main.find("The \\textbf{first} meal.").expect(&main);
+    let input = main.find("\\input{Breakfast/pancakes.tex}").expect(&main);
+    assert!(chapter < intro && intro < input, "{main}");
+}
+
+#[test]
+fn collections_root_makes_every_subfolder_a_chapter() {
+    let project = Project::new();
+    project
+        .recipe("recipes/Dinner", "soup.cook", "Boil.")
+        .recipe("recipes/Breakfast", "pancakes.cook", "Mix.")
+        .file("recipes/notes/todo.txt", "more recipes");
+
+    success(&project.build(&[], &["--collections-root", "recipes"]));
+    let main = project.read("out/main.tex");
+    let breakfast = main.find("\\chapter{Breakfast}").expect(&main);
+    let dinner = main.find("\\chapter{Dinner}").expect(&main);
+    assert!(breakfast < dinner, "{main}");
+    assert!(!main.contains("\\chapter{notes}"), "{main}");
+}
+
+#[test]
+fn explicit_collections_win_over_the_root() {
+    let project = Project::new();
+    project
+        .recipe("recipes/Dinner", "soup.cook", "Boil.")
+        .recipe("recipes/Breakfast", "pancakes.cook", "Mix.");
+
+    success(&project.build(&["recipes/Dinner"], &["--collections-root", "recipes"]));
+    let main = project.read("out/main.tex");
+    assert!(main.contains("\\chapter{Dinner}"), "{main}");
+    assert!(!main.contains("\\chapter{Breakfast}"), "{main}");
+}
+
+#[test]
+fn nested_folders_become_sections() {
+    let project = Project::new();
+    project.recipe("Desserts", "pie.cook", "Bake.").recipe(
+        "Desserts/Cakes/Chocolate",
+        "cake.cook",
+        "Bake longer.",
+    );
+
+    success(&project.build(&["Desserts"], &[]));
+    let main = project.read("out/main.tex");
+    let positions: Vec<_> = [
+        "\\chapter{Desserts}",
+        "\\input{Desserts/pie.tex}",
+        "\\section{Cakes}",
+        "\\subsection{Chocolate}",
+        "\\input{Desserts/Cakes/Chocolate/cake.tex}",
+    ]
+    .iter()
+    .map(|line| main.find(line).expect(&main))
+    .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{main}");
+}
+
+#[test]
+fn references_link_to_nested_recipes() {
+    let project = Project::new();
+    project
+        .recipe(
+            "Desserts",
+            "pie.cook",
+            "Serve with @./Desserts/Cakes/Chocolate/cake{}.",
+        )
+        .recipe(
+            "Desserts/Cakes/Chocolate",
+            "cake.cook",
+            "Serve with @../../pie{}.",
+        );
+
+    success(&project.build(&["Desserts"], &[]));
+    let cake = project.read("out/Desserts/Cakes/Chocolate/cake.tex");
+    assert!(
+        cake.contains("\\label{recipe:Desserts-Cakes-Chocolate:cake}"),
+        "{cake}"
+    );
+    assert!(cake.contains("\\hyperref[recipe:Desserts:pie]{"), "{cake}");
+    let pie = project.read("out/Desserts/pie.tex");
+    assert!(
+        pie.contains("\\hyperref[recipe:Desserts-Cakes-Chocolate:cake]{"),
+        "{pie}"
+    );
+}
+
+#[test]
+fn nested_collections_are_found_in_the_root() {
+    let project = Project::new();
+    project.recipe("recipes/Desserts/Cakes/Chocolate", "cake.cook", "Bake.");
+
+    success(&project.build(&[], &["--collections-root", "recipes"]));
+    let main = project.read("out/main.tex");
+    assert!(main.contains("\\chapter{Desserts}"), "{main}");
+    assert!(main.contains("\\subsection{Chocolate}"), "{main}");
+}
+
+/// The titles of the recipe headers in `latex`, in order.
+fn recipe_headers(latex: &str) -> Vec<&str> {
+    latex
+        .split("\\recipeheader{")
+        .skip(1)
+        .map(|rest| &rest[..rest.find('}').unwrap()])
+        .collect()
+}
+
+#[test]
+fn recipe_numbers_restart_in_every_collection() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .recipe("Breakfast", "toast.cook", "Toast.")
+        .recipe("Dinner", "soup.cook", "Boil.")
+        .recipe("Dinner", "stew.cook", "Simmer.");
+
+    success(&project.run(&[
+        "--standalone",
+        "book.tex",
+        "Breakfast",
+        "Dinner",
+        "--number-recipes",
+    ]));
+    assert_eq!(
+        recipe_headers(&project.read("book.tex")),
+        ["1. pancakes", "2. toast", "1. soup", "2. stew"]
+    );
+
+    success(&project.run(&[
+        "--standalone",
+        "book.tex",
+        "Breakfast",
+        "Dinner",
+        "--number-recipes",
+        "global",
+    ]));
+    assert_eq!(
+        recipe_headers(&project.read("book.tex")),
+        ["1. pancakes", "2. toast", "3. soup", "4. stew"]
+    );
+}
+
+#[test]
+fn units_file_is_read_from_the_environment() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file("units.toml", "[quantity\n");
+
+    let output = project
+        .command()
+        .args(["-l", "template", "-o", "out", "Breakfast"])
+        .env("COOKLATEX_UNITS", "units.toml")
+        .output()
+        .unwrap();
+    let stderr = failure(&output);
+    assert!(stderr.contains("Failed to load units file"), "{stderr}");
+}
+
+#[test]
+fn units_from_the_environment_are_used_to_convert() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Melt @butter{2%stick}.")
+        .file(
+            "units.toml",
+            "[[quantity]]\nquantity = \"mass\"\nunits = [{ names = [\"stick\"], symbols = [\"stick\"], ratio = 113 }]\n",
+        );
+
+    let output = project
+        .command()
+        .args([
+            "-l",
+            "template",
+            "-o",
+            "out",
+            "Breakfast",
+            "--convert",
+            "metric",
+        ])
+        .env("COOKLATEX_UNITS", "units.toml")
+        .output()
+        .unwrap();
+    success(&output);
+    let latex = project.read("out/Breakfast/pancakes.tex");
+    assert!(latex.contains("226 g"), "{latex}");
+}
+
+#[test]
+fn units_file_is_read_from_the_config_folder() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file("config/cooklatex/units.toml", "[quantity\n");
+
+    let stderr = failure(&project.build(&["Breakfast"], &[]));
+    assert!(stderr.contains("Failed to load units file"), "{stderr}");
+}
+
+#[test]
+fn list_systems_prints_the_convert_values() {
+    let project = Project::new();
+
+    let output = project.run(&["list-systems"]);
+    success(&output);
+    assert_eq!(stdout(&output), "metric\nimperial\n");
+}
+
+#[test]
+fn unchanged_recipes_are_not_rewritten() {
+    let project = Project::new();
+    project.recipe("Breakfast", "pancakes.cook", "Mix.").recipe(
+        "Breakfast",
+        "toast.cook",
+        "Toast.",
+    );
+    let modified = |path| {
+        std::fs::metadata(project.path(path))
+            .and_then(|metadata| metadata.modified())
+            .unwrap()
+    };
+
+    success(&project.build(&["Breakfast"], &[]));
+    let pancakes = modified("out/Breakfast/pancakes.tex");
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    project.recipe("Breakfast", "toast.cook", "Toast twice.");
+    success(&project.build(&["Breakfast"], &[]));
+
+    assert_eq!(modified("out/Breakfast/pancakes.tex"), pancakes);
+    assert!(project
+        .read("out/Breakfast/toast.tex")
+        .contains("Toast twice."));
+}
+
+/// Runs the binary with `args`, writing `input` to its standard input.
+fn run_with_stdin(project: &Project, args: &[&str], input: &str) -> std::process::Output {
+    use std::io::Write;
+
+    let mut child = project
+        .command()
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn stdin_recipe_is_printed() {
+    let project = Project::new();
+
+    let output = run_with_stdin(&project, &["--stdin"], &common::recipe("Pancakes", "Mix."));
+    success(&output);
+    let stdout = stdout(&output);
+    assert!(stdout.contains("\\recipeheader{Pancakes}"), "{stdout}");
+    assert!(!project.exists("out"));
+}
+
+#[test]
+fn stdin_recipe_without_metadata_is_titled_recipe() {
+    let project = Project::new();
+
+    let output = run_with_stdin(&project, &["--stdin"], "Mix @flour{200%g}.\n");
+    success(&output);
+    let stdout = stdout(&output);
+    assert!(stdout.contains("\\recipeheader{Recipe}"), "{stdout}");
+    assert!(stdout.contains("\\ingredient{200 g flour}"), "{stdout}");
+}
+
+#[test]
+fn open_without_pdf_only_warns() {
+    let project = Project::new();
+    project.recipe("Breakfast", "pancakes.cook", "Mix.");
+
+    let stderr = success(&project.build(&["Breakfast"], &["--open"]));
+    assert!(
+        stderr.contains("--open has no effect without --pdf"),
+        "{stderr}"
+    );
+    assert!(project.exists("out/main.tex"));
+    assert!(!project.exists("out/main.pdf"));
+}
+
+#[test]
+fn title_from_filename_names_titleless_recipes() {
+    let project = Project::new();
+    project.file(
+        "Baking/banana-bread.cook",
+        "---\nservings: 8\n---\n\nMash the @bananas{3}.\n",
+    );
+
+    let stderr = failure(&project.build(&["Baking"], &["--strict"]));
+    assert!(stderr.contains("Recipe must have a title"), "{stderr}");
+
+    success(&project.build(&["Baking"], &["--force", "--title-from-filename"]));
+    let latex = project.read("out/Baking/banana-bread.tex");
+    assert!(latex.contains(r"\recipeheader{Banana Bread}"), "{latex}");
+}
+
+#[test]
+fn print_schema_prints_the_recipe_schema() {
+    let project = Project::new();
+
+    let output = project.run(&["--print-schema"]);
+    success(&output);
+    let schema = stdout(&output);
+    assert!(schema.contains("\"RecipeJson\""), "{schema}");
+    assert!(schema.contains("\"ingredients\""), "{schema}");
+    assert!(!project.exists("out"));
+}
+
+#[test]
+fn empty_collections_get_no_chapter() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file("Snacks/README.md", "Nothing here yet.\n")
+        .file("Sauces/broken.cook", "Stir without a title.\n");
+
+    success(&project.build(&["Breakfast", "Snacks", "Sauces"], &[]));
+    let main = project.read("out/main.tex");
+    assert!(main.contains("\\chapter{Breakfast}"), "{main}");
+    assert!(!main.contains("\\chapter{Snacks}"), "{main}");
+    assert!(!main.contains("\\chapter{Sauces}"), "{main}");
+}
+
+#[test]
+fn config_file_applies_without_flags() {
+    let project = Project::new();
+    project.recipe("Breakfast", "pancakes.cook", "Mix.").file(
+        "cooklatex.toml",
+        "latex_dir = \"template\"\nlatex_out_dir = \"out\"\ncollections = [\"Breakfast\"]\n",
+    );
+
+    success(&project.run(&[]));
+    let main = project.read("out/main.tex");
+    assert!(main.contains("\\chapter{Breakfast}"), "{main}");
+}
+
+#[test]
+fn flags_override_the_config_file() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .recipe("Dinner", "soup.cook", "Boil.")
+        .file(
+            "settings.toml",
+            "latex_dir = \"template\"\nlatex_out_dir = \"elsewhere\"\ncollections = [\"Breakfast\"]\n",
+        );
+
+    success(&project.run(&["--config", "settings.toml", "-o", "out", "Dinner"]));
+    assert!(!project.exists("elsewhere"));
+    let main = project.read("out/main.tex");
+    assert!(main.contains("\\chapter{Dinner}"), "{main}");
+    assert!(!main.contains("\\chapter{Breakfast}"), "{main}");
+}
+
+#[test]
+fn config_file_sets_any_option() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .recipe("Dinner", "soup.cook", "Boil.")
+        .file(
+            "cooklatex.toml",
+            "collections = [\"Breakfast\", \"Dinner\"]\nnumber_recipes = \"global\"\n",
+        );
+
+    success(&project.run(&["--standalone", "book.tex"]));
+    assert_eq!(
+        recipe_headers(&project.read("book.tex")),
+        ["1. pancakes", "2. soup"]
+    );
+
+    success(&project.run(&["--standalone", "book.tex", "--number-recipes", "collection"]));
+    assert_eq!(
+        recipe_headers(&project.read("book.tex")),
+        ["1. pancakes", "1. soup"]
+    );
+}
+
+#[test]
+fn collections_root_flag_replaces_configured_collections() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .recipe("recipes/Dinner", "soup.cook", "Boil.")
+        .file("cooklatex.toml", "collections = [\"Breakfast\"]\n");
+
+    success(&project.build(&[], &["--collections-root", "recipes"]));
+    let main = project.read("out/main.tex");
+    assert!(main.contains("\\chapter{Dinner}"), "{main}");
+    assert!(!main.contains("\\chapter{Breakfast}"), "{main}");
+}
+
+#[test]
+fn parallel_chapters_follow_the_argument_order() {
+    let project = Project::new();
+    let collections = [
+        "Soups",
+        "Breakfast",
+        "Desserts",
+        "Bread",
+        "Salads",
+        "Drinks",
+        "Sauces",
+        "Appetizers",
+    ];
+    for collection in collections {
+        project
+            .recipe(collection, "first.cook", "Mix.")
+            .recipe(collection, "second.cook", "Stir.");
+    }
+
+    success(&project.build(&collections, &[]));
+    let main = project.read("out/main.tex");
+    let positions: Vec<_> = collections
+        .iter()
+        .map(|collection| {
+            main.find(&format!("\\chapter{{{collection}}}"))
+                .expect(&main)
+        })
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{main}");
+}
+
+#[test]
+fn unrelated_output_files_survive_a_rebuild() {
+    let project = Project::new();
+    project.recipe("Breakfast", "pancakes.cook", "Mix.");
+    success(&project.build(&["Breakfast"], &[]));
+    project.file("out/notes.txt", "My notes");
+
+    success(&project.build(&["Breakfast"], &[]));
+    assert_eq!(project.read("out/notes.txt"), "My notes");
+    assert!(project.exists("out/Breakfast/pancakes.tex"));
+}
+
+#[test]
+fn clean_needs_force_for_a_non_empty_folder() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file("out/notes.txt", "My notes");
+
+    let stderr = failure(&project.build(&["Breakfast"], &["--clean"]));
+    assert!(stderr.contains("use --force"), "{stderr}");
+    assert!(project.exists("out/notes.txt"));
+}
+
+#[test]
+fn clean_only_removes_generated_files() {
+    let project = Project::new();
+    project.recipe("Breakfast", "pancakes.cook", "Mix.").recipe(
+        "Breakfast",
+        "waffles.cook",
+        "Stir.",
+    );
+    success(&project.build(&["Breakfast"], &[]));
+    project.file("out/notes.txt", "My notes");
+    std::fs::remove_file(project.path("Breakfast/waffles.cook")).unwrap();
+
+    success(&project.build(&["Breakfast"], &["--force", "--clean"]));
+    assert_eq!(project.read("out/notes.txt"), "My notes");
+    assert!(project.exists("out/Breakfast/pancakes.tex"));
+    assert!(!project.exists("out/Breakfast/waffles.tex"));
+    assert!(project.exists("out/main.tex"));
+}
+
+#[test]
+fn book_title_lands_in_the_main_file() {
+    let project = Project::new();
+    project
+        .recipe("Breakfast", "pancakes.cook", "Mix.")
+        .file(
+            "template/main.tex",
+            "\\title{%{{book_title}}}\n\\author{%{{book_author}}}\n%{{recipes}}\n",
+        )
+        .file(
+            "book.toml",
+            "title = \"Family Favourites\"\nauthor = \"The Smiths\"\n",
+        );
+
+    success(&project.build(&["Breakfast"], &["--book-meta", "book.toml"]));
+    let main = project.read("out/main.tex");
+    assert!(main.contains("\\title{Family Favourites}"), "{main}");
+    assert!(main.contains("\\author{The Smiths}"), "{main}");
+}
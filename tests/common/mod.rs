@@ -0,0 +1,112 @@
+//! A cookbook project in a temporary folder, for running the binary on.
+
+// Every test binary uses its own part of the fixtures
+#![allow(dead_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use tempfile::TempDir;
+
+/// The `main.tex` of the template, with the recipes placeholder.
+const MAIN_TEX: &str = "\\documentclass{book}\n\\begin{document}\n%{{recipes}}\n\\end{document}\n";
+
+/// A recipe titled `title` serving 2, with `body` as its steps.
+pub fn recipe(title: &str, body: &str) -> String {
+    format!("---\ntitle: {title}\nservings: 2\n---\n\n{body}\n")
+}
+
+/// A folder with a LaTeX template in `template`, recipe collections and an
+/// empty config folder, so no user configuration is picked up.
+pub struct Project {
+    dir: TempDir,
+}
+
+impl Project {
+    pub fn new() -> Self {
+        let project = Self {
+            dir: tempfile::tempdir().expect("failed to create a temporary folder"),
+        };
+        project.file("template/main.tex", MAIN_TEX);
+        std::fs::create_dir_all(project.path("config")).expect("failed to create config folder");
+        project
+    }
+
+    /// Writes `contents` to the file at `path` within the project.
+    pub fn file(&self, path: &str, contents: &str) -> &Self {
+        let path = self.path(path);
+        std::fs::create_dir_all(path.parent().unwrap()).expect("failed to create the folder");
+        std::fs::write(path, contents).expect("failed to write the file");
+        self
+    }
+
+    /// Writes the recipe `file_name`, titled after its stem, into `collection`.
+    pub fn recipe(&self, collection: &str, file_name: &str, body: &str) -> &Self {
+        let title = Path::new(file_name).file_stem().unwrap().to_str().unwrap();
+        self.file(&format!("{collection}/{file_name}"), &recipe(title, body))
+    }
+
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.dir.path().join(path)
+    }
+
+    /// Whether the file at `path` exists.
+    pub fn exists(&self, path: &str) -> bool {
+        self.path(path).exists()
+    }
+
+    /// The contents of the file at `path`.
+    pub fn read(&self, path: &str) -> String {
+        std::fs::read_to_string(self.path(path))
+            .unwrap_or_else(|e| panic!("failed to read {path}: {e}"))
+    }
+
+    /// The binary, run in the project without any arguments.
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_cooklatex"));
+        command
+            .current_dir(self.dir.path())
+            .env("XDG_CONFIG_HOME", self.path("config"))
+            .env_remove("COOKLATEX_UNITS")
+            .env_remove("RUST_LOG");
+        command
+    }
+
+    /// Runs the binary with `args`.
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command()
+            .args(args)
+            .output()
+            .expect("failed to run cooklatex")
+    }
+
+    /// Transpiles the `collections` from the template into `out`, with the
+    /// further `args`.
+    pub fn build(&self, collections: &[&str], args: &[&str]) -> Output {
+        let mut all = vec!["-l", "template", "-o", "out"];
+        all.extend(collections);
+        all.extend(args);
+        self.run(&all)
+    }
+}
+
+/// The standard error of a run, asserting it succeeded.
+pub fn success(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(output.status.success(), "cooklatex failed: {stderr}");
+    stderr
+}
+
+/// The standard error of a run, asserting it failed.
+pub fn failure(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(!output.status.success(), "cooklatex succeeded: {stderr}");
+    stderr
+}
+
+/// The standard output of a run.
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}